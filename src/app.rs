@@ -1,17 +1,23 @@
 use glib::ControlFlow;
 use gtk::prelude::*;
 use std::cell::RefCell;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-
-use crate::audio::AudioEngine;
-use crate::monitor::SystemMonitor;
+use std::time::{Duration, Instant};
+
+use crate::audio::{AudioEngine, Renderer};
+use crate::config::Settings;
+use crate::monitor::{
+    DemoSource, MetricRecorder, MetricReplay, MetricSource, ProcessSelector, SystemMetrics,
+    SystemMonitor,
+};
 use crate::pack::{PackLoader, SoundPack};
 use crate::ui::{RefreshRate, StartupDialog, TrayCallbacks, TrayManager};
 
 /// Main application state
 pub struct App {
     packs_dir: PathBuf,
+    settings: Settings,
     available_packs: Vec<SoundPack>,
     selected_pack_index: Option<usize>,
     audio_engine: Rc<RefCell<AudioEngine>>,
@@ -21,36 +27,80 @@ pub struct App {
     tray: Option<TrayManager>,
     startup_dialog: Option<StartupDialog>,
     update_source_id: Option<glib::SourceId>,
+    /// When the update loop last ticked, to smooth over the time that actually passed
+    last_tick: Option<Instant>,
+    /// Waits for a pack's sounds to be decoded before loading it
+    pending_load: Option<glib::SourceId>,
+    /// Where metric snapshots are written, if recording
+    recorder: Option<MetricRecorder>,
+    /// Recording that replaces the live metrics, if replaying
+    replay: Option<MetricReplay>,
+    /// Whether a pack is being previewed from the startup dialog
+    previewing: bool,
+    /// Pack being monitored with, restored when a preview stops
+    monitoring_pack_index: Option<usize>,
+    /// Disk devices the per-device voices are laid out for
+    disk_devices: Vec<String>,
 }
 
 impl App {
-    pub fn new(packs_dir: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
-        let loader = PackLoader::new(&packs_dir);
+    pub fn new(packs_dir: PathBuf, settings: Settings) -> Result<Self, Box<dyn std::error::Error>> {
+        let loader = PackLoader::new(&packs_dir).with_response_overrides(&settings.responses);
         let available_packs = loader.scan_packs()?;
 
         let audio_engine = AudioEngine::new()?;
+        let system_monitor = SystemMonitor::with_settings(&settings);
+        if let Some(path) = system_monitor.cgroup_path() {
+            println!("Monitoring cgroup: {}", path.display());
+        }
+        let disk_devices: Vec<String> = if settings.disk_per_device {
+            system_monitor.disk_device_names().iter().map(|n| n.to_string()).collect()
+        } else {
+            Vec::new()
+        };
+        if settings.disk_per_device {
+            println!("Disk devices: {}", disk_devices.join(", "));
+        }
 
         Ok(Self {
             packs_dir,
+            settings,
             available_packs,
             selected_pack_index: None,
             audio_engine: Rc::new(RefCell::new(audio_engine)),
-            system_monitor: Rc::new(RefCell::new(SystemMonitor::new())),
+            system_monitor: Rc::new(RefCell::new(system_monitor)),
             refresh_rate: RefreshRate::Normal,
             is_monitoring: false,
             tray: None,
             startup_dialog: None,
             update_source_id: None,
+            last_tick: None,
+            pending_load: None,
+            recorder: None,
+            replay: None,
+            previewing: false,
+            monitoring_pack_index: None,
+            disk_devices,
         })
     }
 
     /// Reload available packs from disk
     pub fn reload_packs(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let loader = PackLoader::new(&self.packs_dir);
+        let loader = PackLoader::new(&self.packs_dir).with_response_overrides(&self.settings.responses);
         self.available_packs = loader.scan_packs()?;
         Ok(())
     }
 
+    /// Write every metric snapshot to a recording
+    pub fn set_recorder(&mut self, recorder: MetricRecorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Play metrics from a recording instead of monitoring the system
+    pub fn set_replay(&mut self, replay: MetricReplay) {
+        self.replay = Some(replay);
+    }
+
     /// Get available packs
     pub fn packs(&self) -> &[SoundPack] {
         &self.available_packs
@@ -94,6 +144,72 @@ impl App {
         }
     }
 
+    /// Render a pack's audio for the replayed (or demo) metrics into a file, faster than
+    /// real time. Without a duration a recording is rendered once through, a demo for
+    /// four sweeps. Returns the length rendered.
+    pub fn render(
+        &self,
+        pack_name: &str,
+        path: &Path,
+        duration: Option<Duration>,
+    ) -> Result<Duration, String> {
+        let pack = self
+            .available_packs
+            .iter()
+            .find(|p| p.name().eq_ignore_ascii_case(pack_name))
+            .cloned()
+            .ok_or_else(|| format!("Pack '{}' not found", pack_name))?;
+
+        let (mut metrics_at, full_length): (Box<dyn FnMut(Duration) -> SystemMetrics + '_>, f64) =
+            match (&self.replay, self.settings.demo) {
+                (Some(replay), _) => {
+                    let mut previous = None;
+                    let metrics_at = move |time: Duration| {
+                        let elapsed = time.as_secs_f64();
+                        let metrics = replay.at(elapsed, previous);
+                        previous = Some(elapsed);
+                        metrics
+                    };
+                    (Box::new(metrics_at), replay.duration())
+                }
+                (None, Some(pattern)) => {
+                    let template = self.system_monitor.borrow_mut().refresh();
+                    let mut demo = DemoSource::new(pattern, &pack.metric_ids(), &template);
+                    let metrics_at = move |time: Duration| {
+                        demo.advance_to(time.as_secs_f64());
+                        let mut metrics = SystemMetrics::default();
+                        metrics.insert(demo.values());
+                        metrics
+                    };
+                    (Box::new(metrics_at), DemoSource::DEFAULT_PERIOD * 4.0)
+                }
+                (None, None) => return Err("Rendering needs --replay or --demo".to_string()),
+            };
+        let duration = duration.unwrap_or_else(|| Duration::from_secs_f64(full_length));
+
+        // Voices follow the trace rather than this machine, which may not be where it was recorded
+        let first = metrics_at(Duration::ZERO);
+        let num_cores = first.instances("cpu.core").len().max(1);
+        let num_disk_devices = if self.settings.disk_per_device {
+            first.instances("disk.device").len()
+        } else {
+            0
+        };
+
+        let step = Duration::from_millis(self.refresh_rate.as_millis() as u64);
+        let renderer = Renderer::new(path, step).map_err(|e| e.to_string())?;
+        let mut engine = self.audio_engine.borrow_mut();
+        engine.set_output(renderer.output());
+        engine
+            .load_pack(pack, num_cores, num_disk_devices)
+            .map_err(|e| e.to_string())?;
+        renderer
+            .render(&mut engine, duration, metrics_at)
+            .map_err(|e| e.to_string())?;
+
+        Ok(duration)
+    }
+
     /// Show the startup dialog
     pub fn show_startup_dialog(app: Rc<RefCell<Self>>) {
         let dialog = StartupDialog::new();
@@ -111,12 +227,41 @@ impl App {
             if let Some(app) = app_weak.upgrade() {
                 // Use try_borrow_mut to avoid panic if app is already borrowed
                 // (can happen when set_packs triggers row_selected during list clearing)
-                if let Ok(mut app_ref) = app.try_borrow_mut() {
-                    app_ref.selected_pack_index = index;
+                let previewing = match app.try_borrow_mut() {
+                    Ok(mut app_ref) => {
+                        app_ref.selected_pack_index = index;
+                        // Decode the pack while the user decides, so starting it is instant
+                        if let Some(pack) = index.and_then(|i| app_ref.available_packs.get(i)) {
+                            app_ref.audio_engine.borrow().preload(pack);
+                        }
+                        app_ref.previewing
+                    }
+                    Err(_) => false,
+                };
+
+                // Keep previewing as the selection moves through the list
+                if let (true, Some(index)) = (previewing, index) {
+                    Self::start_preview(&app, index);
                 }
             }
         });
 
+        // Handle preview button
+        let app_weak = Rc::downgrade(&app);
+        dialog.connect_preview(move || {
+            let Some(app) = app_weak.upgrade() else {
+                return false;
+            };
+
+            if app.borrow().previewing {
+                Self::stop_preview(&app);
+                return false;
+            }
+
+            let selected = app.borrow().selected_pack_index;
+            selected.is_some_and(|index| Self::start_preview(&app, index))
+        });
+
         // Handle start button
         let app_weak = Rc::downgrade(&app);
         let dialog_window = dialog.window().clone();
@@ -135,6 +280,9 @@ impl App {
         dialog.window().connect_delete_event(move |_, _| {
             // If monitoring, just hide; otherwise quit
             if let Some(app) = app_weak.upgrade() {
+                if app.borrow().previewing {
+                    Self::stop_preview(&app);
+                }
                 if app.borrow().is_monitoring {
                     return glib::Propagation::Stop;
                 }
@@ -162,17 +310,21 @@ impl App {
             }
         };
 
+        // Decoding happens in the background, so come back once the pack is ready
+        if Self::wait_for_decode(&app, &pack, move |app| {
+            Self::start_monitoring(app.clone(), pack_index)
+        }) {
+            return;
+        }
+
         let pack_name = pack.name().to_string();
 
+        Self::end_preview(&app);
+        app.borrow_mut().monitoring_pack_index = Some(pack_index);
+
         // Load the pack into audio engine
-        {
-            let app_ref = app.borrow();
-            let num_cores = app_ref.system_monitor.borrow().core_count();
-            let mut engine = app_ref.audio_engine.borrow_mut();
-            if let Err(e) = engine.load_pack(pack, num_cores) {
-                eprintln!("Failed to load pack: {}", e);
-                return;
-            }
+        if !app.borrow().load_pack(pack) {
+            return;
         }
 
         // Check if tray already exists - if so, just update the pack name
@@ -230,6 +382,33 @@ impl App {
                         }
                     })
                 },
+                on_network_toggled: {
+                    let app_weak = app_weak.clone();
+                    Box::new(move |enabled| {
+                        if let Some(app) = app_weak.upgrade() {
+                            app.borrow().audio_engine.borrow_mut().set_network_enabled(enabled);
+                        }
+                    })
+                },
+                on_other_toggled: {
+                    let app_weak = app_weak.clone();
+                    Box::new(move |enabled| {
+                        if let Some(app) = app_weak.upgrade() {
+                            app.borrow().audio_engine.borrow_mut().set_other_enabled(enabled);
+                        }
+                    })
+                },
+                on_process_selected: {
+                    let app_weak = app_weak.clone();
+                    Box::new(move |name| {
+                        if let Some(app) = app_weak.upgrade() {
+                            let selector = name
+                                .map(|n| ProcessSelector::by_name(&n))
+                                .unwrap_or_default();
+                            app.borrow().system_monitor.borrow_mut().set_process_selector(selector);
+                        }
+                    })
+                },
                 on_show_window: {
                     let app_weak = app_weak.clone();
                     Box::new(move || {
@@ -246,7 +425,20 @@ impl App {
             };
 
             tray.set_callbacks(callbacks);
+
+            // Start the toggles off as the engine has them
+            {
+                let app_ref = app.borrow();
+                let engine = app_ref.audio_engine.borrow();
+                tray.set_cpu_enabled(engine.cpu_enabled());
+                tray.set_ram_enabled(engine.ram_enabled());
+                tray.set_disk_enabled(engine.disk_enabled());
+                tray.set_network_enabled(engine.network_enabled());
+                tray.set_other_enabled(engine.other_enabled());
+            }
             app.borrow_mut().tray = Some(tray);
+
+            Self::start_process_list_updates(&app);
         }
 
         app.borrow_mut().is_monitoring = true;
@@ -276,14 +468,8 @@ impl App {
         };
 
         // Load the pack into audio engine
-        {
-            let app_ref = app.borrow();
-            let num_cores = app_ref.system_monitor.borrow().core_count();
-            let mut engine = app_ref.audio_engine.borrow_mut();
-            if let Err(e) = engine.load_pack(pack, num_cores) {
-                eprintln!("Failed to load pack: {}", e);
-                return;
-            }
+        if !app.borrow().load_pack(pack) {
+            return;
         }
 
         // No tray in headless mode
@@ -299,6 +485,176 @@ impl App {
         Self::start_update_loop(app);
     }
 
+    /// Load a pack into the audio engine, reporting failures
+    fn load_pack(&self, pack: SoundPack) -> bool {
+        // The demo sweeps whatever this pack's voices follow
+        let demo = match self.previewing {
+            true => Some(self.settings.demo.unwrap_or_default()),
+            false => self.settings.demo,
+        };
+        if demo.is_some() {
+            self.system_monitor.borrow_mut().set_demo(demo, &pack.metric_ids());
+        }
+
+        let num_cores = self.system_monitor.borrow().core_count();
+        let num_disk_devices = self.disk_voice_count();
+        let mut engine = self.audio_engine.borrow_mut();
+        if let Err(e) = engine.load_pack(pack, num_cores, num_disk_devices) {
+            eprintln!("Failed to load pack: {}", e);
+            return false;
+        }
+        true
+    }
+
+    /// Have a pack's sounds decoded in the background and call `then` once they are,
+    /// leaving the UI and the current pack running meanwhile. Returns false, without
+    /// calling `then`, if the pack can be loaded right away. Replaces an earlier wait.
+    fn wait_for_decode(
+        app: &Rc<RefCell<Self>>,
+        pack: &SoundPack,
+        then: impl Fn(&Rc<RefCell<Self>>) + 'static,
+    ) -> bool {
+        Self::cancel_pending_load(app);
+
+        let engine = Rc::clone(&app.borrow().audio_engine);
+        engine.borrow().preload(pack);
+        if !engine.borrow().is_decoding(pack) {
+            return false;
+        }
+
+        let pack = pack.clone();
+        let app_weak = Rc::downgrade(app);
+        let source_id = glib::timeout_add_local(Duration::from_millis(50), move || {
+            let Some(app) = app_weak.upgrade() else {
+                return ControlFlow::Break;
+            };
+            if app.borrow().audio_engine.borrow().is_decoding(&pack) {
+                return ControlFlow::Continue;
+            }
+            app.borrow_mut().pending_load = None;
+            then(&app);
+            ControlFlow::Break
+        });
+        app.borrow_mut().pending_load = Some(source_id);
+        true
+    }
+
+    /// Stop waiting to load a pack
+    fn cancel_pending_load(app: &Rc<RefCell<Self>>) {
+        if let Some(source_id) = app.borrow_mut().pending_load.take() {
+            source_id.remove();
+        }
+    }
+
+    /// Play a pack with the demo load, so it can be heard across its whole range.
+    /// Returns whether the preview is playing.
+    fn start_preview(app: &Rc<RefCell<Self>>, pack_index: usize) -> bool {
+        let pack = match app.borrow().available_packs.get(pack_index).cloned() {
+            Some(p) => p,
+            None => return false,
+        };
+
+        app.borrow_mut().previewing = true;
+
+        if Self::wait_for_decode(app, &pack, move |app| {
+            Self::start_preview(app, pack_index);
+        }) {
+            return true;
+        }
+
+        if !app.borrow().load_pack(pack) {
+            Self::stop_preview(app);
+            return false;
+        }
+
+        if let Err(e) = app.borrow().audio_engine.borrow_mut().play() {
+            eprintln!("Failed to start playback: {}", e);
+        }
+
+        if app.borrow().update_source_id.is_none() {
+            Self::start_update_loop(app.clone());
+        }
+        true
+    }
+
+    /// Stop a preview, going back to the pack being monitored (or silence)
+    fn stop_preview(app: &Rc<RefCell<Self>>) {
+        Self::cancel_pending_load(app);
+        Self::end_preview(app);
+
+        let monitoring_pack = {
+            let app_ref = app.borrow();
+            match app_ref.monitoring_pack_index {
+                Some(index) if app_ref.is_monitoring => app_ref.available_packs.get(index).cloned(),
+                _ => None,
+            }
+        };
+
+        match monitoring_pack {
+            Some(pack) => {
+                if Self::wait_for_decode(app, &pack, Self::stop_preview) {
+                    return;
+                }
+                let app_ref = app.borrow();
+                if app_ref.load_pack(pack) {
+                    if let Err(e) = app_ref.audio_engine.borrow_mut().play() {
+                        eprintln!("Failed to start playback: {}", e);
+                    }
+                }
+            }
+            None => {
+                let mut app_ref = app.borrow_mut();
+                if let Some(source_id) = app_ref.update_source_id.take() {
+                    source_id.remove();
+                }
+                let result = app_ref.audio_engine.borrow_mut().stop();
+                if let Err(e) = result {
+                    eprintln!("Error stopping audio: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Leave preview mode, putting back the configured metrics
+    fn end_preview(app: &Rc<RefCell<Self>>) {
+        let mut app_ref = app.borrow_mut();
+        if !app_ref.previewing {
+            return;
+        }
+
+        app_ref.previewing = false;
+        // Loading the next pack starts the configured demo again, if any
+        app_ref.system_monitor.borrow_mut().set_demo(None, &[]);
+        if let Some(ref dialog) = app_ref.startup_dialog {
+            dialog.set_previewing(false);
+        }
+    }
+
+    /// Number of per-device disk voices to create (0 when per-device voices are off)
+    fn disk_voice_count(&self) -> usize {
+        self.disk_devices.len()
+    }
+
+    /// Lay the per-device disk voices out again when a disk was attached or removed
+    fn follow_disk_devices(&mut self) {
+        if !self.settings.disk_per_device || self.replay.is_some() {
+            return;
+        }
+
+        let monitor = self.system_monitor.borrow();
+        let names = monitor.disk_device_names();
+        if names == self.disk_devices {
+            return;
+        }
+
+        println!("Disk devices: {}", names.join(", "));
+        let result = self.audio_engine.borrow_mut().reload(monitor.core_count(), names.len());
+        if let Err(e) = result {
+            eprintln!("Failed to reload pack: {}", e);
+        }
+        self.disk_devices = names.iter().map(|n| n.to_string()).collect();
+    }
+
     /// Show pack selector (for changing packs while running)
     fn show_pack_selector(app: Rc<RefCell<Self>>) {
         // Reload packs in case new ones were added
@@ -339,6 +695,11 @@ impl App {
     fn start_update_loop(app: Rc<RefCell<Self>>) {
         let interval = app.borrow().refresh_rate.as_millis();
 
+        // Only one loop may run, e.g. when switching packs while monitoring
+        if let Some(source_id) = app.borrow_mut().update_source_id.take() {
+            source_id.remove();
+        }
+
         let app_weak = Rc::downgrade(&app);
         let source_id = glib::timeout_add_local(
             std::time::Duration::from_millis(interval as u64),
@@ -352,18 +713,73 @@ impl App {
             },
         );
 
-        app.borrow_mut().update_source_id = Some(source_id);
+        let mut app_ref = app.borrow_mut();
+        app_ref.update_source_id = Some(source_id);
+        app_ref.last_tick = None;
+    }
+
+    /// Periodically refresh the tray's list of processes to watch
+    fn start_process_list_updates(app: &Rc<RefCell<Self>>) {
+        const PROCESS_LIST_SIZE: usize = 10;
+
+        let update = |app: &Rc<RefCell<Self>>| {
+            let app_ref = app.borrow();
+            let mut monitor = app_ref.system_monitor.borrow_mut();
+            let names = monitor.top_process_names(PROCESS_LIST_SIZE);
+
+            // Only a single watched name can be shown as the tray's current choice
+            let selector = monitor.process_selector();
+            let watched = match selector.names.as_slice() {
+                [name] if selector.pids.is_empty() && selector.parents.is_empty() => Some(name.as_str()),
+                _ => None,
+            };
+
+            if let Some(ref tray) = app_ref.tray {
+                tray.set_top_processes(&names, watched);
+            }
+        };
+
+        update(app);
+
+        let app_weak = Rc::downgrade(app);
+        glib::timeout_add_seconds_local(5, move || {
+            if let Some(app) = app_weak.upgrade() {
+                update(&app);
+                ControlFlow::Continue
+            } else {
+                ControlFlow::Break
+            }
+        });
     }
 
     /// Single update tick - refresh metrics and update audio
     fn update_tick(app: &Rc<RefCell<Self>>) {
-        let app_ref = app.borrow();
+        let app_ref = &mut *app.borrow_mut();
+
+        // Refresh system metrics, or take them from a recording. Previews always play
+        // the demo, and are left out of recordings.
+        let metrics = match app_ref.replay {
+            Some(ref mut replay) if !app_ref.previewing => replay.current(),
+            _ => app_ref.system_monitor.borrow_mut().refresh(),
+        };
 
-        // Refresh system metrics
-        let metrics = app_ref.system_monitor.borrow_mut().refresh();
+        app_ref.follow_disk_devices();
 
-        // Update audio engine
-        app_ref.audio_engine.borrow_mut().update(&metrics);
+        if let (Some(recorder), false) = (app_ref.recorder.as_mut(), app_ref.previewing) {
+            if let Err(e) = recorder.record(&metrics) {
+                eprintln!("Failed to record metrics, recording stopped: {}", e);
+                app_ref.recorder = None;
+            }
+        }
+
+        // Update audio engine, smoothing over the time since the last tick (which runs
+        // late under load), or one refresh interval on the first tick
+        let now = Instant::now();
+        let elapsed = match app_ref.last_tick.replace(now) {
+            Some(last_tick) => now.duration_since(last_tick),
+            None => Duration::from_millis(app_ref.refresh_rate.as_millis() as u64),
+        };
+        app_ref.audio_engine.borrow_mut().update(&metrics, elapsed);
     }
 
     /// Clean shutdown
@@ -0,0 +1,243 @@
+use gstreamer as gst;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+use super::pcm::{decode_file, Pcm};
+
+/// How much decoded audio to keep once it is no longer playing (512 MiB)
+const BUDGET_BYTES: usize = 512 * 1024 * 1024;
+
+/// Decodes a sound file into samples
+type Decoder = fn(&Path) -> Result<Pcm, gst::glib::BoolError>;
+
+/// Sound files decoded into memory, shared by every pipeline and kept across pack
+/// switches, so each file is only decoded once.
+///
+/// Files no longer in use are dropped, least recently used first, once the cache grows
+/// past its budget.
+#[derive(Clone)]
+pub struct SampleCache {
+    shared: Arc<(Mutex<CacheState>, Condvar)>,
+    decode: Decoder,
+    /// Bytes of unused files to keep at most
+    budget: usize,
+}
+
+impl Default for SampleCache {
+    fn default() -> Self {
+        Self::new(decode_file, BUDGET_BYTES)
+    }
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<PathBuf, CacheEntry>,
+    /// Files being decoded right now, by another thread or the preloader
+    decoding: HashSet<PathBuf>,
+    /// Files the preloader has yet to decode
+    queued: Vec<PathBuf>,
+    /// File the preloader is on, while its thread is running
+    preloading: Option<PathBuf>,
+    /// Counter for ordering uses
+    uses: u64,
+}
+
+struct CacheEntry {
+    pcm: Arc<Pcm>,
+    last_used: u64,
+}
+
+impl SampleCache {
+    fn new(decode: Decoder, budget: usize) -> Self {
+        Self {
+            shared: Arc::default(),
+            decode,
+            budget,
+        }
+    }
+
+    /// A decoded sound file, decoding it unless it is cached (or waiting for it if it
+    /// is being decoded already)
+    pub(super) fn get(&self, file_path: &Path) -> Result<Arc<Pcm>, gst::glib::BoolError> {
+        let decoded = &self.shared.1;
+        let mut state = self.lock();
+        loop {
+            state.uses += 1;
+            let uses = state.uses;
+            if let Some(entry) = state.entries.get_mut(file_path) {
+                entry.last_used = uses;
+                return Ok(Arc::clone(&entry.pcm));
+            }
+            if !state.decoding.contains(file_path) {
+                break;
+            }
+            state = decoded.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+        state.decoding.insert(file_path.to_path_buf());
+        drop(state);
+
+        let result = (self.decode)(file_path).map(Arc::new);
+
+        let mut state = self.lock();
+        state.decoding.remove(file_path);
+        if let Ok(ref pcm) = result {
+            let last_used = state.uses;
+            state.entries.insert(
+                file_path.to_path_buf(),
+                CacheEntry {
+                    pcm: Arc::clone(pcm),
+                    last_used,
+                },
+            );
+            state.evict(self.budget);
+        }
+        decoded.notify_all();
+        result
+    }
+
+    /// Decode files in the background, so loading them later is instant.
+    /// Replaces files queued by an earlier call that haven't been decoded yet.
+    pub fn preload(&self, files: Vec<PathBuf>) {
+        let mut state = self.lock();
+        let queued = files
+            .into_iter()
+            .rev()
+            .filter(|path| !state.entries.contains_key(path))
+            .collect();
+        state.queued = queued;
+        if state.preloading.is_some() || state.queued.is_empty() {
+            return;
+        }
+        state.preloading = state.queued.last().cloned();
+        drop(state);
+
+        let cache = self.clone();
+        std::thread::spawn(move || loop {
+            let next = {
+                let mut state = cache.lock();
+                let next = state.queued.pop();
+                state.preloading.clone_from(&next);
+                next
+            };
+            let Some(path) = next else {
+                break;
+            };
+            if let Err(e) = cache.get(&path) {
+                eprintln!("Warning: {}", e);
+            }
+        });
+    }
+
+    /// Whether any of `files` is still waiting to be decoded
+    pub fn is_pending(&self, files: &[PathBuf]) -> bool {
+        let state = self.lock();
+        files.iter().any(|path| {
+            state.queued.contains(path)
+                || state.decoding.contains(path)
+                || state.preloading.as_ref() == Some(path)
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        self.shared.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl CacheState {
+    /// Drop unused files, least recently used first, until the cache fits `budget`
+    fn evict(&mut self, budget: usize) {
+        let mut size: usize = self.entries.values().map(|e| e.pcm.size_bytes()).sum();
+        while size > budget {
+            // Only the cache holds an unused file
+            let oldest = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| Arc::strong_count(&entry.pcm) == 1)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone());
+            let Some(path) = oldest else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&path) {
+                size -= entry.pcm.size_bytes();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Samples in every fake sound
+    const SAMPLES: usize = 1000;
+    const SOUND_BYTES: usize = SAMPLES * std::mem::size_of::<f32>();
+
+    fn decode_silence(_: &Path) -> Result<Pcm, gst::glib::BoolError> {
+        Ok(Pcm::new(vec![0.0; SAMPLES], 1))
+    }
+
+    fn cached(cache: &SampleCache) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = cache.lock().entries.keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn least_recently_used_files_are_evicted_first() {
+        let cache = SampleCache::new(decode_silence, 3 * SOUND_BYTES);
+        let paths: Vec<PathBuf> = ["a", "b", "c", "d"].iter().map(PathBuf::from).collect();
+
+        for path in &paths[..3] {
+            cache.get(path).unwrap();
+        }
+        // Using "a" again leaves "b" the least recently used
+        cache.get(&paths[0]).unwrap();
+        cache.get(&paths[3]).unwrap();
+        assert_eq!(cached(&cache), [paths[0].clone(), paths[2].clone(), paths[3].clone()]);
+
+        let size: usize = cache.lock().entries.values().map(|e| e.pcm.size_bytes()).sum();
+        assert!(size <= 3 * SOUND_BYTES);
+    }
+
+    #[test]
+    fn files_in_use_are_kept_over_budget() {
+        let cache = SampleCache::new(decode_silence, SOUND_BYTES);
+        let playing = cache.get(Path::new("a")).unwrap();
+        cache.get(Path::new("b")).unwrap();
+        cache.get(Path::new("c")).unwrap();
+        // "a" is the oldest but still playing, so "b" makes room instead
+        assert_eq!(cached(&cache), [PathBuf::from("a"), PathBuf::from("c")]);
+        drop(playing);
+    }
+
+    #[test]
+    fn default_budget_is_512_mib() {
+        assert_eq!(SampleCache::default().budget, 512 * 1024 * 1024);
+    }
+
+    #[test]
+    fn get_waits_for_a_preload_of_the_same_file() {
+        static DECODES: AtomicUsize = AtomicUsize::new(0);
+        fn decode_slowly(path: &Path) -> Result<Pcm, gst::glib::BoolError> {
+            DECODES.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(100));
+            decode_silence(path)
+        }
+
+        let cache = SampleCache::new(decode_slowly, BUDGET_BYTES);
+        let path = PathBuf::from("slow");
+        cache.preload(vec![path.clone()]);
+        while !cache.lock().decoding.contains(&path) {
+            std::thread::yield_now();
+        }
+
+        let pcm = cache.get(&path).unwrap();
+        assert_eq!(pcm.size_bytes(), SOUND_BYTES);
+        assert_eq!(DECODES.load(Ordering::SeqCst), 1);
+        assert!(Arc::ptr_eq(&pcm, &cache.lock().entries[&path].pcm));
+    }
+}
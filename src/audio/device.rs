@@ -0,0 +1,164 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use super::cache::SampleCache;
+use super::pcm::{Feed, FeedMode};
+
+/// Frames pushed each time an appsrc runs low (about 85ms)
+const CHUNK_FRAMES: usize = 4096;
+
+/// The pipeline every sound is played in on the audio device.
+///
+/// Each sound, looping or one-shot, is a branch feeding one `audiomixer` and one sink,
+/// so a pack runs on one clock and shows up as a single stream in the system's volume
+/// mixer.
+pub struct DeviceGraph {
+    pipeline: gst::Pipeline,
+    mixer: gst::Element,
+    feeds: RefCell<Vec<Arc<Mutex<Feed>>>>,
+    /// Decoded sounds, shared with other pipelines
+    cache: SampleCache,
+    /// How long the end of each loop crossfades into its start
+    loop_crossfade: Cell<Duration>,
+    _bus_watch: gst::bus::BusWatchGuard,
+}
+
+impl DeviceGraph {
+    /// A pipeline playing sounds decoded into `cache`
+    pub fn new(cache: SampleCache) -> Result<Self, gst::glib::BoolError> {
+        let pipeline = gst::Pipeline::new();
+        let mixer = gst::ElementFactory::make("audiomixer").build()?;
+        let convert = gst::ElementFactory::make("audioconvert").build()?;
+        let resample = gst::ElementFactory::make("audioresample").build()?;
+        let sink = gst::ElementFactory::make("autoaudiosink").build()?;
+
+        pipeline.add_many([&mixer, &convert, &resample, &sink])?;
+        gst::Element::link_many([&mixer, &convert, &resample, &sink])?;
+
+        let bus_watch = pipeline.bus().unwrap().add_watch_local(move |_, msg| {
+            if let gst::MessageView::Error(err) = msg.view() {
+                eprintln!(
+                    "GStreamer error: {} ({:?})",
+                    err.error(),
+                    err.debug()
+                );
+            }
+            gst::glib::ControlFlow::Continue
+        })?;
+
+        Ok(Self {
+            pipeline,
+            mixer,
+            feeds: RefCell::new(Vec::new()),
+            cache,
+            loop_crossfade: Cell::new(Duration::ZERO),
+            _bus_watch: bus_watch,
+        })
+    }
+
+    /// The pipeline sounds are built in
+    pub(super) fn pipeline(&self) -> &gst::Pipeline {
+        &self.pipeline
+    }
+
+    /// Add an appsrc playing `file_path` to the pipeline.
+    /// The sound is decoded into memory, so a loop plays without a gap.
+    /// Returns the element and the feed's index, for triggering one-shots.
+    pub(super) fn add_source(
+        &self,
+        file_path: &Path,
+        mode: FeedMode,
+    ) -> Result<(gst::Element, usize), gst::glib::BoolError> {
+        let mut pcm = self.cache.get(file_path)?;
+        if mode == FeedMode::Loop {
+            pcm = pcm.looped(self.loop_crossfade.get());
+        }
+
+        let appsrc = pcm.appsrc()?;
+        if mode == FeedMode::OneShot {
+            // Queue a single chunk, so a one-shot is heard soon after it is triggered
+            appsrc.set_property("max-bytes", (CHUNK_FRAMES * pcm.frame_bytes()) as u64);
+        }
+        self.pipeline.add(&appsrc)?;
+
+        // Top the appsrc up whenever it runs low. The graph owns the feed, which owns
+        // the appsrc, so the handler only holds on weakly.
+        let feed = Arc::new(Mutex::new(Feed::new(appsrc.clone(), pcm, mode)));
+        let feed_weak = Arc::downgrade(&feed);
+        appsrc.connect("need-data", false, move |_| {
+            let feed = feed_weak.upgrade()?;
+            let mut feed = feed.lock().unwrap_or_else(PoisonError::into_inner);
+            let flow = feed.push(CHUNK_FRAMES);
+            if flow != gst::FlowReturn::Ok && flow != gst::FlowReturn::Flushing {
+                eprintln!("Warning: Failed to push audio ({:?})", flow);
+            }
+            None
+        });
+        let mut feeds = self.feeds.borrow_mut();
+        feeds.push(feed);
+
+        Ok((appsrc, feeds.len() - 1))
+    }
+
+    /// Start a one-shot feed from the beginning of its sound
+    pub(super) fn trigger(&self, feed: usize) {
+        if let Some(feed) = self.feeds.borrow().get(feed) {
+            feed.lock().unwrap_or_else(PoisonError::into_inner).trigger();
+        }
+    }
+
+    /// Crossfade the end of loops added from now on into their start
+    pub(super) fn set_loop_crossfade(&self, crossfade: Duration) {
+        self.loop_crossfade.set(crossfade);
+    }
+
+    /// Feed `last` into the mixer
+    pub(super) fn link_output(&self, last: &gst::Element) -> Result<(), gst::glib::BoolError> {
+        let mixer_pad = self
+            .mixer
+            .request_pad_simple("sink_%u")
+            .ok_or_else(|| gst::glib::bool_error!("Failed to request a mixer pad"))?;
+        let src_pad = last
+            .static_pad("src")
+            .ok_or_else(|| gst::glib::bool_error!("Element has no src pad"))?;
+        src_pad
+            .link(&mixer_pad)
+            .map_err(|e| gst::glib::bool_error!("Failed to link to the mixer: {:?}", e))?;
+        Ok(())
+    }
+
+    /// Start the pipeline. Returns false if it failed to start.
+    pub(super) fn play(&self) -> bool {
+        if self.pipeline.set_state(gst::State::Playing).is_err() {
+            return false;
+        }
+        // Wait for state change to complete (up to 1 second)
+        let _ = self.pipeline.state(gst::ClockTime::from_seconds(1));
+        true
+    }
+
+    /// Stop the pipeline. Returns false if it failed to stop.
+    pub(super) fn stop(&self) -> bool {
+        if self.pipeline.set_state(gst::State::Null).is_err() {
+            return false;
+        }
+        // Wait for state change to complete (up to 500ms)
+        let _ = self.pipeline.state(gst::ClockTime::from_mseconds(500));
+
+        // The next start begins a new timeline
+        for feed in self.feeds.borrow().iter() {
+            feed.lock().unwrap_or_else(PoisonError::into_inner).rewind();
+        }
+        true
+    }
+}
+
+impl Drop for DeviceGraph {
+    fn drop(&mut self) {
+        let _ = self.pipeline.set_state(gst::State::Null);
+    }
+}
@@ -1,17 +1,22 @@
 use gstreamer as gst;
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::time::Duration;
 
-use super::mixer::{AudioChannel, AudioMixer, CpuPlayback, PerCoreCpuPlayer};
+use super::cache::SampleCache;
+use super::mixer::{
+    AudioChannel, AudioMixer, AudioOutput, CpuPlayback, OneShotSound, PerCoreCpuPlayer,
+};
 use super::pitch::GranularPitchElement;
 use crate::monitor::SystemMetrics;
-use crate::pack::SoundPack;
+use crate::pack::{PitchSource, SoundPack};
 
 #[derive(Debug)]
 pub enum AudioEngineError {
     GstreamerInit(gst::glib::Error),
     GstreamerError(gst::glib::BoolError),
     NoPackLoaded,
+    RenderFailed(String),
 }
 
 impl std::fmt::Display for AudioEngineError {
@@ -20,6 +25,7 @@ impl std::fmt::Display for AudioEngineError {
             Self::GstreamerInit(e) => write!(f, "GStreamer initialization error: {}", e),
             Self::GstreamerError(e) => write!(f, "GStreamer error: {}", e),
             Self::NoPackLoaded => write!(f, "No sound pack loaded"),
+            Self::RenderFailed(e) => write!(f, "Render failed: {}", e),
         }
     }
 }
@@ -41,8 +47,17 @@ pub struct AudioEngine {
     cpu_enabled: bool,
     ram_enabled: bool,
     disk_enabled: bool,
+    network_enabled: bool,
+    /// Every other channel (swap, load, pressure, processes, power, external metrics...)
+    other_enabled: bool,
     /// Whether using per-core CPU or averaged
     use_averages: bool,
+    /// What drives per-core CPU pitch
+    pitch_source: PitchSource,
+    /// Where loaded packs are played (None until a pack is loaded on the audio device)
+    output: Option<AudioOutput>,
+    /// Decoded sound files, kept across pack switches
+    cache: SampleCache,
 }
 
 impl AudioEngine {
@@ -59,76 +74,138 @@ impl AudioEngine {
             cpu_enabled: true,
             ram_enabled: true,
             disk_enabled: true,
+            network_enabled: true,
+            other_enabled: true,
             use_averages: true,
+            pitch_source: PitchSource::Load,
+            output: None,
+            cache: SampleCache::default(),
         })
     }
 
-    /// Load a sound pack and prepare for playback
-    pub fn load_pack(&mut self, pack: SoundPack, num_cpu_cores: usize) -> Result<(), AudioEngineError> {
+    /// Play packs loaded from now on to `output` instead of the audio device
+    pub fn set_output(&mut self, output: AudioOutput) {
+        self.output = Some(output);
+    }
+
+    /// Decode a pack's sounds in the background, so loading it later is instant
+    pub fn preload(&self, pack: &SoundPack) {
+        self.cache.preload(pack.sound_files());
+    }
+
+    /// Whether a pack's sounds are still being decoded in the background, in which case
+    /// loading it now would wait for them
+    pub fn is_decoding(&self, pack: &SoundPack) -> bool {
+        self.cache.is_pending(&pack.sound_files())
+    }
+
+    /// Load a sound pack and prepare for playback.
+    /// `num_disk_devices` > 0 gives each monitored disk device its own panned voice.
+    pub fn load_pack(
+        &mut self,
+        pack: SoundPack,
+        num_cpu_cores: usize,
+        num_disk_devices: usize,
+    ) -> Result<(), AudioEngineError> {
         // Stop current playback
         self.stop()?;
 
         let mut mixer = self.mixer.borrow_mut();
         mixer.clear();
 
+        // The old pack's branches go with its pipeline. The audio device is only opened
+        // here, so rendering works without one.
+        let output = match self.output.take() {
+            Some(AudioOutput::Render(graph)) => AudioOutput::Render(graph),
+            _ => AudioOutput::device(&self.cache)?,
+        };
+        let output = &*self.output.insert(output);
+        output.set_loop_crossfade(pack.config.loop_crossfade);
+
         let config = &pack.config;
-        let slide_interval = config.slide_interval;
+        let smoothing = config.smoothing;
         let freq_fluct = config.frequency_fluctuation;
         self.use_averages = config.use_averages;
+        self.pitch_source = config.pitch_source;
 
         // Create CPU playback
         if pack.cpu_sounds.has_sounds() {
             if let Some(primary_path) = &pack.cpu_sounds.primary {
                 if config.use_averages {
                     // Single averaged CPU channel, centered
-                    let cpu_channel = AudioChannel::new(
+                    let mut cpu_channel = AudioChannel::new(
                         config.cpu_mode,
                         Some(primary_path.as_path()),
                         pack.cpu_sounds.secondary.as_deref(),
-                        slide_interval,
+                        smoothing,
                         freq_fluct,
                         0.0, // center
+                        output,
                     )?;
+                    cpu_channel.set_response(config.cpu_response.clone());
+                    cpu_channel.set_pitch(config.cpu_pitch.clone());
                     mixer.cpu_playback = Some(CpuPlayback::Averaged(cpu_channel));
                 } else {
                     // Per-core mode: single source split to multiple panned outputs
                     // This ensures perfect sync - no stereo position weirdness on loop
                     // Uses lightweight granular pitch shifting per core
-                    let player = PerCoreCpuPlayer::new(
+                    let mut player = PerCoreCpuPlayer::new(
                         primary_path,
                         num_cpu_cores,
-                        slide_interval,
+                        smoothing,
                         freq_fluct,
+                        output,
                     )?;
+                    player.set_response(config.cpu_response.clone());
+                    player.set_pitch(config.cpu_pitch.clone());
                     mixer.cpu_playback = Some(CpuPlayback::PerCore(player));
                 }
             }
         }
 
-        // Create RAM channel (centered)
-        if pack.ram_sounds.has_sounds() {
-            let ram_channel = AudioChannel::new(
-                config.ram_mode,
-                pack.ram_sounds.primary.as_deref(),
-                pack.ram_sounds.secondary.as_deref(),
-                slide_interval,
-                freq_fluct,
-                0.0, // center
-            )?;
-            mixer.ram_channel = Some(ram_channel);
+        // Per-device mode: one voice per device, spread left to right like CPU cores.
+        // It takes the place of every channel following a disk metric.
+        let disk_per_device = num_disk_devices > 0 && pack.disk_sounds.has_sounds();
+        if disk_per_device {
+            // In fade mode the active sound is the one that should rise with activity
+            let path = pack.disk_sounds.secondary.as_ref().or(pack.disk_sounds.primary.as_ref());
+            if let Some(path) = path {
+                let mut player = PerCoreCpuPlayer::new(
+                    path,
+                    num_disk_devices,
+                    smoothing,
+                    freq_fluct,
+                    output,
+                )?;
+                player.set_response(config.disk_response.clone());
+                player.set_pitch(config.disk_pitch.clone());
+                mixer.disk_device_player = Some(player);
+            }
         }
 
-        // Create Disk channel (centered)
-        if pack.disk_sounds.has_sounds() {
-            let disk_channel = AudioChannel::new(
-                config.disk_mode,
-                pack.disk_sounds.primary.as_deref(),
-                pack.disk_sounds.secondary.as_deref(),
-                slide_interval,
+        // Create a channel for every metric the pack binds a sound to
+        for channel in &pack.channels {
+            if !channel.sounds.has_sounds() || (disk_per_device && channel.metric.starts_with("disk.")) {
+                continue;
+            }
+            let mut audio_channel = AudioChannel::new(
+                channel.mode,
+                channel.sounds.primary.as_deref(),
+                channel.sounds.secondary.as_deref(),
+                smoothing,
                 freq_fluct,
-                0.0, // center
+                channel.pan,
+                output,
             )?;
-            mixer.disk_channel = Some(disk_channel);
+            audio_channel.set_response(channel.response.clone());
+            audio_channel.set_pitch(channel.pitch.clone());
+            mixer.metric_channels.push((channel.metric.clone(), audio_channel));
+        }
+
+        // Load event one-shots (power connected/disconnected)
+        for trigger in &pack.triggers {
+            let sound = OneShotSound::new(&trigger.sound, output)?;
+            mixer.triggers.push((trigger.metric.clone(), sound));
         }
 
         drop(mixer);
@@ -137,20 +214,39 @@ impl AudioEngine {
         Ok(())
     }
 
+    /// Load the current pack again with a different number of CPU cores or disk
+    /// devices, carrying on playing if it was
+    pub fn reload(&mut self, num_cpu_cores: usize, num_disk_devices: usize) -> Result<(), AudioEngineError> {
+        let Some(pack) = self.current_pack.clone() else {
+            return Ok(());
+        };
+
+        let was_playing = self.is_playing;
+        self.load_pack(pack, num_cpu_cores, num_disk_devices)?;
+        if was_playing {
+            self.play()?;
+        }
+        Ok(())
+    }
+
     /// Start audio playback
     pub fn play(&mut self) -> Result<(), AudioEngineError> {
         if self.current_pack.is_none() {
             return Err(AudioEngineError::NoPackLoaded);
         }
 
-        self.mixer.borrow().play_all();
+        if let Some(ref output) = self.output {
+            output.play()?;
+        }
         self.is_playing = true;
         Ok(())
     }
 
     /// Stop audio playback
     pub fn stop(&mut self) -> Result<(), AudioEngineError> {
-        self.mixer.borrow().stop_all();
+        if let Some(ref output) = self.output {
+            output.stop()?;
+        }
         self.is_playing = false;
 
         // Reset channel values
@@ -160,65 +256,87 @@ impl AudioEngine {
             Some(CpuPlayback::PerCore(player)) => player.reset(),
             None => {}
         }
-        if let Some(ref mut ch) = mixer.ram_channel {
-            ch.reset();
+        if let Some(ref mut player) = mixer.disk_device_player {
+            player.reset();
         }
-        if let Some(ref mut ch) = mixer.disk_channel {
+        for (_, ch) in &mut mixer.metric_channels {
             ch.reset();
         }
 
         Ok(())
     }
 
-    /// Update audio based on current system metrics
-    pub fn update(&mut self, metrics: &SystemMetrics) {
+    /// Update audio based on current system metrics, `elapsed` after the previous update
+    pub fn update(&mut self, metrics: &SystemMetrics, elapsed: Duration) {
         let mut mixer = self.mixer.borrow_mut();
 
         // Update CPU playback
         match &mut mixer.cpu_playback {
             Some(CpuPlayback::Averaged(ch)) => {
                 if self.cpu_enabled {
-                    ch.update(metrics.cpu_average.get());
+                    ch.update(metrics.get("cpu.average").get(), elapsed);
                 } else {
-                    ch.update(0.0);
+                    ch.update(0.0, elapsed);
                 }
             }
             Some(CpuPlayback::PerCore(player)) => {
+                let cores = metrics.instances("cpu.core");
+                let frequencies = metrics.instances("cpu.frequency");
                 for i in 0..player.core_count() {
+                    let pitch = match self.pitch_source {
+                        PitchSource::Frequency => frequencies.get(i).map(|v| v.get()),
+                        PitchSource::Load => None,
+                    };
                     if self.cpu_enabled {
-                        let value = metrics.cpu_cores.get(i)
+                        let value = cores.get(i)
                             .map(|v| v.get())
                             .unwrap_or(0.0);
-                        player.update_core(i, value);
+                        player.update_core(i, value, pitch, elapsed);
                     } else {
-                        player.update_core(i, 0.0);
+                        player.update_core(i, 0.0, pitch, elapsed);
                     }
                 }
             }
             None => {}
         }
 
-        // Update RAM channel
-        if self.ram_enabled {
-            if let Some(ref mut ch) = mixer.ram_channel {
-                ch.update(metrics.memory.get());
-            }
-        } else {
-            if let Some(ref mut ch) = mixer.ram_channel {
-                ch.update(0.0);
+        // Update per-device disk voices
+        if let Some(ref mut player) = mixer.disk_device_player {
+            let devices = metrics.instances("disk.device");
+            for i in 0..player.core_count() {
+                if self.disk_enabled {
+                    let value = devices.get(i)
+                        .map(|v| v.get())
+                        .unwrap_or(0.0);
+                    player.update_core(i, value, None, elapsed);
+                } else {
+                    player.update_core(i, 0.0, None, elapsed);
+                }
             }
         }
 
-        // Update Disk channel
-        if self.disk_enabled {
-            if let Some(ref mut ch) = mixer.disk_channel {
-                ch.update(metrics.disk.get());
-            }
-        } else {
-            if let Some(ref mut ch) = mixer.disk_channel {
-                ch.update(0.0);
+        // Update every other channel from the metric it is bound to
+        for (metric, ch) in &mut mixer.metric_channels {
+            if self.metric_enabled(metric) {
+                ch.update(metrics.get(metric).get(), elapsed);
+            } else {
+                ch.update(0.0, elapsed);
             }
         }
+
+        // Play one-shots for events that fired since the last update
+        mixer.play_triggers(|metric| self.metric_enabled(metric) && metrics.get(metric).get() >= 1.0);
+    }
+
+    /// Whether the user has muted the group a metric belongs to
+    fn metric_enabled(&self, metric: &str) -> bool {
+        match metric.split('.').next() {
+            Some("cpu") => self.cpu_enabled,
+            Some("memory") => self.ram_enabled,
+            Some("disk") => self.disk_enabled,
+            Some("network") => self.network_enabled,
+            _ => self.other_enabled,
+        }
     }
 
     pub fn set_master_volume(&mut self, volume: f64) {
@@ -236,6 +354,34 @@ impl AudioEngine {
     pub fn set_disk_enabled(&mut self, enabled: bool) {
         self.disk_enabled = enabled;
     }
+
+    pub fn set_network_enabled(&mut self, enabled: bool) {
+        self.network_enabled = enabled;
+    }
+
+    pub fn set_other_enabled(&mut self, enabled: bool) {
+        self.other_enabled = enabled;
+    }
+
+    pub fn cpu_enabled(&self) -> bool {
+        self.cpu_enabled
+    }
+
+    pub fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    pub fn disk_enabled(&self) -> bool {
+        self.disk_enabled
+    }
+
+    pub fn network_enabled(&self) -> bool {
+        self.network_enabled
+    }
+
+    pub fn other_enabled(&self) -> bool {
+        self.other_enabled
+    }
 }
 
 impl Default for AudioEngine {
@@ -1,126 +1,180 @@
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+use super::cache::SampleCache;
+use super::device::DeviceGraph;
+use super::pcm::FeedMode;
+use super::render::RenderGraph;
+use crate::pack::{PitchResponse, Response, Smoothing, SoundMode};
+
+/// Where the mixer's sounds are played
+#[derive(Clone)]
+pub enum AudioOutput {
+    /// On the default audio device, every sound a branch of one live pipeline
+    Device(Rc<DeviceGraph>),
+    /// Mixed into a render pipeline, driven by a `Renderer`
+    Render(Rc<RenderGraph>),
+}
+
+impl AudioOutput {
+    /// A new pipeline on the default audio device, playing sounds decoded into `cache`
+    pub fn device(cache: &SampleCache) -> Result<Self, gst::glib::BoolError> {
+        Ok(Self::Device(Rc::new(DeviceGraph::new(cache.clone())?)))
+    }
+
+    /// The pipeline sounds are built in
+    fn pipeline(&self) -> &gst::Pipeline {
+        match self {
+            Self::Device(graph) => graph.pipeline(),
+            Self::Render(graph) => graph.pipeline(),
+        }
+    }
 
-use crate::pack::SoundMode;
+    /// Add a source playing the sound file to the pipeline.
+    /// Returns the element and the feed's index, for triggering one-shots.
+    fn add_source(
+        &self,
+        file_path: &Path,
+        mode: FeedMode,
+    ) -> Result<(gst::Element, usize), gst::glib::BoolError> {
+        match self {
+            Self::Device(graph) => graph.add_source(file_path, mode),
+            Self::Render(graph) => graph.add_source(file_path, mode),
+        }
+    }
 
-/// Represents a single audio playback element with stereo panning
+    /// Feed `last` into the mix
+    fn link_output(&self, last: &gst::Element) -> Result<(), gst::glib::BoolError> {
+        match self {
+            Self::Device(graph) => graph.link_output(last),
+            Self::Render(graph) => graph.link_output(last),
+        }
+    }
+
+    /// Play the sound file on a loop into `first`, and `last` into the output.
+    /// The elements from `first` to `last` must already be added and linked.
+    fn connect(
+        &self,
+        file_path: &Path,
+        first: &gst::Element,
+        last: &gst::Element,
+    ) -> Result<(), gst::glib::BoolError> {
+        let (source, _) = self.add_source(file_path, FeedMode::Loop)?;
+        source.link(first)?;
+        self.link_output(last)
+    }
+
+    /// Start a one-shot feed from the beginning of its sound
+    fn trigger(&self, feed: usize) {
+        match self {
+            Self::Device(graph) => graph.trigger(feed),
+            Self::Render(graph) => graph.trigger(feed),
+        }
+    }
+
+    /// Crossfade the end of loops connected from now on into their start
+    pub(super) fn set_loop_crossfade(&self, crossfade: Duration) {
+        match self {
+            Self::Device(graph) => graph.set_loop_crossfade(crossfade),
+            Self::Render(graph) => graph.set_loop_crossfade(crossfade),
+        }
+    }
+
+    /// Start playing on the audio device. A render is driven by its `Renderer` instead.
+    pub fn play(&self) -> Result<(), gst::glib::BoolError> {
+        match self {
+            Self::Device(graph) if !graph.play() => {
+                Err(gst::glib::bool_error!("Failed to start audio pipeline"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Stop playing on the audio device
+    pub fn stop(&self) -> Result<(), gst::glib::BoolError> {
+        match self {
+            Self::Device(graph) if !graph.stop() => {
+                Err(gst::glib::bool_error!("Failed to stop audio pipeline"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Represents a single audio playback element with stereo panning,
+/// and optionally pitch shifting
 struct PlaybackElement {
-    pipeline: gst::Pipeline,
     volume_element: gst::Element,
     panorama_element: Option<gst::Element>,
-    _bus_watch: gst::bus::BusWatchGuard,
+    /// Granular pitch shifter, only present when the pack enables FrequencyFluctuation
+    pitch_element: Option<gst::Element>,
 }
 
 impl PlaybackElement {
-    fn new(file_path: &Path, pan: f64) -> Result<Self, gst::glib::BoolError> {
-        // Ensure we have an absolute path
-        let abs_path = if file_path.is_absolute() {
-            file_path.to_path_buf()
-        } else {
-            std::env::current_dir()
-                .unwrap_or_default()
-                .join(file_path)
-        };
-
-        let uri = format!("file://{}", abs_path.display());
-
-        // Create pipeline elements
-        let pipeline = gst::Pipeline::new();
-
-        let source = gst::ElementFactory::make("uridecodebin")
-            .property("uri", &uri)
-            .build()?;
-
+    fn new(
+        file_path: &Path,
+        pan: f64,
+        with_pitch: bool,
+        output: &AudioOutput,
+    ) -> Result<Self, gst::glib::BoolError> {
         // Queue for buffering and thread decoupling
         let queue = gst::ElementFactory::make("queue").build()?;
         let convert = gst::ElementFactory::make("audioconvert").build()?;
         let resample = gst::ElementFactory::make("audioresample").build()?;
+        let mut elements = vec![queue.clone(), convert, resample];
+
+        // Granular pitch shifter behind a capsfilter to ensure the F32 format it needs,
+        // as in PerCoreCpuPlayer. Left out entirely when pitch never changes.
+        let pitch_element = if with_pitch {
+            let capsfilter = gst::ElementFactory::make("capsfilter")
+                .property(
+                    "caps",
+                    gst::Caps::builder("audio/x-raw")
+                        .field("format", "F32LE")
+                        .field("layout", "interleaved")
+                        .build(),
+                )
+                .build()?;
+            let pitch = gst::ElementFactory::make("granularpitch")
+                .property("pitch", 1.0f64)
+                .build()?;
+            elements.push(capsfilter);
+            elements.push(pitch.clone());
+            Some(pitch)
+        } else {
+            None
+        };
 
         let volume_element = gst::ElementFactory::make("volume")
             .property("volume", 0.0f64)
             .build()?;
+        elements.push(volume_element.clone());
 
         // Try to create panorama element for stereo panning
         let panorama_element = gst::ElementFactory::make("audiopanorama")
             .property("panorama", pan as f32)
             .build()
             .ok();
-
-        let sink = gst::ElementFactory::make("autoaudiosink").build()?;
-
-        // Add elements to pipeline and link them
         if let Some(ref pan_elem) = panorama_element {
-            pipeline.add_many([&source, &queue, &convert, &resample, &volume_element, pan_elem, &sink])?;
-            gst::Element::link_many([&queue, &convert, &resample, &volume_element, pan_elem, &sink])?;
-        } else {
-            pipeline.add_many([&source, &queue, &convert, &resample, &volume_element, &sink])?;
-            gst::Element::link_many([&queue, &convert, &resample, &volume_element, &sink])?;
+            elements.push(pan_elem.clone());
         }
 
-        // Connect uridecodebin's pad-added signal to link to queue
-        let queue_weak = queue.downgrade();
-        source.connect_pad_added(move |_, src_pad| {
-            if let Some(queue) = queue_weak.upgrade() {
-                if let Some(sink_pad) = queue.static_pad("sink") {
-                    if !sink_pad.is_linked() {
-                        let _ = src_pad.link(&sink_pad);
-                    }
-                }
-            }
-        });
-
-        // Set up bus watch for looping and error handling
-        let pipeline_weak = pipeline.downgrade();
-        let bus_watch = pipeline.bus().unwrap().add_watch_local(move |_, msg| {
-            match msg.view() {
-                gst::MessageView::Eos(_) => {
-                    if let Some(pipeline) = pipeline_weak.upgrade() {
-                        // Simple seek back to start for looping
-                        let _ = pipeline.seek_simple(
-                            gst::SeekFlags::FLUSH,
-                            gst::ClockTime::ZERO,
-                        );
-                    }
-                }
-                gst::MessageView::Error(err) => {
-                    eprintln!(
-                        "GStreamer error: {} ({:?})",
-                        err.error(),
-                        err.debug()
-                    );
-                }
-                _ => {}
-            }
-            gst::glib::ControlFlow::Continue
-        })?;
+        // Add elements to pipeline and link them
+        output.pipeline().add_many(&elements)?;
+        gst::Element::link_many(&elements)?;
+        let last = elements.last().unwrap_or(&volume_element);
+        output.connect(file_path, &queue, last)?;
 
         Ok(Self {
-            pipeline,
             volume_element,
             panorama_element,
-            _bus_watch: bus_watch,
+            pitch_element,
         })
     }
 
-    fn play(&self) {
-        if self.pipeline.set_state(gst::State::Playing).is_err() {
-            eprintln!("Failed to start audio pipeline");
-            return;
-        }
-        // Wait for state change to complete (up to 1 second)
-        let _ = self.pipeline.state(gst::ClockTime::from_seconds(1));
-    }
-
-    fn stop(&self) {
-        if self.pipeline.set_state(gst::State::Null).is_err() {
-            eprintln!("Failed to stop audio pipeline");
-            return;
-        }
-        // Wait for state change to complete (up to 500ms)
-        let _ = self.pipeline.state(gst::ClockTime::from_mseconds(500));
-    }
-
     fn set_volume(&self, volume: f64) {
         self.volume_element.set_property("volume", volume.clamp(0.0, 1.0));
     }
@@ -131,84 +185,93 @@ impl PlaybackElement {
         }
     }
 
-    fn set_rate(&self, _rate: f64) {
-        // Pitch shifting disabled for PlaybackElement to avoid audio issues
-        // Per-core CPU mode uses PerCoreCpuPlayer which has pitch support
+    fn set_rate(&self, rate: f64) {
+        if let Some(ref pitch) = self.pitch_element {
+            pitch.set_property("pitch", rate.clamp(0.25, 4.0));
+        }
     }
 }
 
-impl Drop for PlaybackElement {
-    fn drop(&mut self) {
-        let _ = self.pipeline.set_state(gst::State::Null);
+/// A sound that plays once each time it is triggered, rather than looping.
+/// It is a one-shot feed of the output's pipeline, through its own volume.
+pub struct OneShotSound {
+    output: AudioOutput,
+    feed: usize,
+    volume: gst::Element,
+}
+
+impl OneShotSound {
+    pub fn new(file_path: &Path, output: &AudioOutput) -> Result<Self, gst::glib::BoolError> {
+        let (source, feed) = output.add_source(file_path, FeedMode::OneShot)?;
+        let convert = gst::ElementFactory::make("audioconvert").build()?;
+        let resample = gst::ElementFactory::make("audioresample").build()?;
+        let volume = gst::ElementFactory::make("volume").build()?;
+
+        output.pipeline().add_many([&convert, &resample, &volume])?;
+        gst::Element::link_many([&source, &convert, &resample, &volume])?;
+        output.link_output(&volume)?;
+
+        Ok(Self {
+            output: output.clone(),
+            feed,
+            volume,
+        })
+    }
+
+    /// Play the sound from the start
+    pub fn trigger(&self, volume: f64) {
+        self.volume.set_property("volume", volume.clamp(0.0, 1.0));
+        self.output.trigger(self.feed);
     }
 }
 
 /// A single pipeline that plays one audio file through multiple panned outputs.
-/// Used for per-core CPU mode where all cores must stay perfectly in sync.
+/// Used for per-core CPU mode where all cores must stay perfectly in sync,
+/// and for per-device disk voices.
 /// Uses tee to split one source to N panned branches, mixed back together.
 /// Per-core pitch shifting uses lightweight granular synthesis (not SoundTouch).
 pub struct PerCoreCpuPlayer {
-    pipeline: gst::Pipeline,
     /// Volume elements for each core (index = core number)
     volume_elements: Vec<gst::Element>,
     /// Pitch elements for each core (granular pitch shifter)
     pitch_elements: Vec<gst::Element>,
     /// Current smoothed values per core
     current_values: Vec<f64>,
-    /// Transition speed
-    transition_speed: f64,
+    /// Current smoothed pitch-driving values per core
+    pitch_values: Vec<f64>,
+    /// How quickly each core follows its value
+    smoothing: Smoothing,
     /// Master volume
     master_volume: f64,
     /// Whether pitch fluctuation is enabled
     frequency_fluctuation: bool,
-    _bus_watch: gst::bus::BusWatchGuard,
+    /// How each core's smoothed value maps onto its volume
+    response: Response,
+    /// How each core's smoothed pitch value maps onto its pitch
+    pitch: PitchResponse,
 }
 
 impl PerCoreCpuPlayer {
     pub fn new(
         file_path: &Path,
         num_cores: usize,
-        slide_interval: u32,
+        smoothing: Smoothing,
         frequency_fluctuation: bool,
+        output: &AudioOutput,
     ) -> Result<Self, gst::glib::BoolError> {
-        let abs_path = if file_path.is_absolute() {
-            file_path.to_path_buf()
-        } else {
-            std::env::current_dir()
-                .unwrap_or_default()
-                .join(file_path)
-        };
-
-        let uri = format!("file://{}", abs_path.display());
-        let pipeline = gst::Pipeline::new();
+        let pipeline = output.pipeline();
 
-        // Source and initial processing
-        let source = gst::ElementFactory::make("uridecodebin")
-            .property("uri", &uri)
-            .build()?;
+        // Initial processing
         let convert = gst::ElementFactory::make("audioconvert").build()?;
         let resample = gst::ElementFactory::make("audioresample").build()?;
         let tee = gst::ElementFactory::make("tee").build()?;
 
-        // Final mixer and sink
+        // Final mixer, played on the output
         let mixer = gst::ElementFactory::make("audiomixer").build()?;
-        let sink = gst::ElementFactory::make("autoaudiosink").build()?;
 
-        pipeline.add_many([&source, &convert, &resample, &tee, &mixer, &sink])?;
+        pipeline.add_many([&convert, &resample, &tee, &mixer])?;
         gst::Element::link_many([&convert, &resample, &tee])?;
-        gst::Element::link_many([&mixer, &sink])?;
-
-        // Connect source to convert
-        let convert_weak = convert.downgrade();
-        source.connect_pad_added(move |_, src_pad| {
-            if let Some(convert) = convert_weak.upgrade() {
-                if let Some(sink_pad) = convert.static_pad("sink") {
-                    if !sink_pad.is_linked() {
-                        let _ = src_pad.link(&sink_pad);
-                    }
-                }
-            }
-        });
+        output.connect(file_path, &convert, &mixer)?;
 
         // Create a branch for each core with panning and pitch
         let mut volume_elements = Vec::with_capacity(num_cores);
@@ -281,86 +344,53 @@ impl PerCoreCpuPlayer {
             pitch_elements.push(pitch);
         }
 
-        // Set up looping
-        let pipeline_weak = pipeline.downgrade();
-        let bus_watch = pipeline.bus().unwrap().add_watch_local(move |_, msg| {
-            match msg.view() {
-                gst::MessageView::Eos(_) => {
-                    if let Some(pipeline) = pipeline_weak.upgrade() {
-                        // Simple seek back to start for looping
-                        let _ = pipeline.seek_simple(
-                            gst::SeekFlags::FLUSH,
-                            gst::ClockTime::ZERO,
-                        );
-                    }
-                }
-                gst::MessageView::Error(err) => {
-                    eprintln!(
-                        "GStreamer error: {} ({:?})",
-                        err.error(),
-                        err.debug()
-                    );
-                }
-                _ => {}
-            }
-            gst::glib::ControlFlow::Continue
-        })?;
-
-        let transition_speed = 1.0 / (slide_interval as f64).max(1.0);
-
         Ok(Self {
-            pipeline,
             volume_elements,
             pitch_elements,
             current_values: vec![0.0; num_cores],
-            transition_speed,
+            pitch_values: vec![0.0; num_cores],
+            smoothing,
             master_volume: 1.0,
             frequency_fluctuation,
-            _bus_watch: bus_watch,
+            response: Response::default(),
+            pitch: PitchResponse::default(),
         })
     }
 
-    pub fn play(&self) {
-        if self.pipeline.set_state(gst::State::Playing).is_err() {
-            eprintln!("Failed to start per-core CPU audio pipeline");
-            return;
-        }
-        // Wait for state change to complete (up to 1 second)
-        let _ = self.pipeline.state(gst::ClockTime::from_seconds(1));
-    }
-
-    pub fn stop(&self) {
-        if self.pipeline.set_state(gst::State::Null).is_err() {
-            eprintln!("Failed to stop per-core CPU audio pipeline");
-            return;
-        }
-        // Wait for state change to complete (up to 500ms)
-        let _ = self.pipeline.state(gst::ClockTime::from_mseconds(500));
-    }
-
-    /// Update a specific core's volume and pitch based on its CPU usage
-    pub fn update_core(&mut self, core_index: usize, target_value: f64) {
+    /// Update a specific core's volume and pitch based on its CPU usage, `elapsed` after
+    /// the previous update. `pitch_value` drives pitch separately (e.g. core frequency);
+    /// when None, pitch follows usage.
+    pub fn update_core(
+        &mut self,
+        core_index: usize,
+        target_value: f64,
+        pitch_value: Option<f64>,
+        elapsed: Duration,
+    ) {
         if core_index >= self.volume_elements.len() {
             return;
         }
 
         let target = target_value.clamp(0.0, 1.0);
-        self.current_values[core_index] +=
-            (target - self.current_values[core_index]) * self.transition_speed;
+        self.current_values[core_index] =
+            self.smoothing.step(self.current_values[core_index], target, elapsed);
 
-        let smoothed = self.current_values[core_index];
+        let pitch_target = pitch_value.map(|v| v.clamp(0.0, 1.0)).unwrap_or(target);
+        self.pitch_values[core_index] =
+            self.smoothing.step(self.pitch_values[core_index], pitch_target, elapsed);
+
+        let level = self.response.apply(self.current_values[core_index]);
 
         // Update volume - normalize by sqrt of cores for balanced mixing
         // Using sqrt means: 4 cores divides by 2, 8 cores by ~2.8, 16 cores by 4
         // This keeps individual cores audible while preventing excessive summing
         let num_cores = self.volume_elements.len() as f64;
-        let volume = (smoothed * self.master_volume) / num_cores.sqrt();
+        let volume = (level * self.master_volume) / num_cores.sqrt();
         self.volume_elements[core_index].set_property("volume", volume.clamp(0.0, 1.0));
 
         // Update pitch if frequency fluctuation is enabled
         if self.frequency_fluctuation {
-            // Map 0.0-1.0 to pitch range 0.8-1.2
-            let pitch = 0.8 + smoothed * 0.4;
+            let pitch = self.pitch.apply(self.pitch_values[core_index]).clamp(0.25, 4.0);
             self.pitch_elements[core_index].set_property("pitch", pitch);
         }
     }
@@ -369,10 +399,23 @@ impl PerCoreCpuPlayer {
         self.master_volume = volume.clamp(0.0, 1.0);
     }
 
+    /// Set how the smoothed value maps onto volume
+    pub fn set_response(&mut self, response: Response) {
+        self.response = response;
+    }
+
+    /// Set how the smoothed value maps onto pitch
+    pub fn set_pitch(&mut self, pitch: PitchResponse) {
+        self.pitch = pitch;
+    }
+
     pub fn reset(&mut self) {
         for v in &mut self.current_values {
             *v = 0.0;
         }
+        for v in &mut self.pitch_values {
+            *v = 0.0;
+        }
     }
 
     pub fn core_count(&self) -> usize {
@@ -380,12 +423,6 @@ impl PerCoreCpuPlayer {
     }
 }
 
-impl Drop for PerCoreCpuPlayer {
-    fn drop(&mut self) {
-        let _ = self.pipeline.set_state(gst::State::Null);
-    }
-}
-
 /// A single audio channel that can operate in different modes
 pub struct AudioChannel {
     mode: SoundMode,
@@ -395,10 +432,14 @@ pub struct AudioChannel {
     secondary: Option<PlaybackElement>,
     /// Current smoothed value for transitions
     current_value: f64,
-    /// Transition speed (derived from SlideInterval)
-    transition_speed: f64,
+    /// How quickly the channel follows its value
+    smoothing: Smoothing,
     /// Enable frequency/pitch fluctuation
     frequency_fluctuation: bool,
+    /// How the smoothed value maps onto volume (and the crossfade)
+    response: Response,
+    /// How the smoothed value maps onto pitch
+    pitch: PitchResponse,
     /// Master volume multiplier
     master_volume: f64,
 }
@@ -408,59 +449,45 @@ impl AudioChannel {
         mode: SoundMode,
         primary_path: Option<&Path>,
         secondary_path: Option<&Path>,
-        slide_interval: u32,
+        smoothing: Smoothing,
         frequency_fluctuation: bool,
         pan: f64,
+        output: &AudioOutput,
     ) -> Result<Self, gst::glib::BoolError> {
+        // In fade mode only the active sound changes pitch
         let primary = primary_path
-            .map(|p| PlaybackElement::new(p, pan))
+            .map(|p| {
+                let with_pitch = frequency_fluctuation && mode == SoundMode::Volume;
+                PlaybackElement::new(p, pan, with_pitch, output)
+            })
             .transpose()?;
         let secondary = secondary_path
-            .map(|p| PlaybackElement::new(p, pan))
+            .map(|p| PlaybackElement::new(p, pan, frequency_fluctuation, output))
             .transpose()?;
 
-        // Convert SlideInterval to transition speed
-        // Higher SlideInterval = slower transitions
-        let transition_speed = 1.0 / (slide_interval as f64).max(1.0);
-
         Ok(Self {
             mode,
             primary,
             secondary,
             current_value: 0.0,
-            transition_speed,
+            smoothing,
             frequency_fluctuation,
+            response: Response::default(),
+            pitch: PitchResponse::default(),
             master_volume: 1.0,
         })
     }
 
-    pub fn play(&self) {
-        // Play primary first, then secondary
-        // Each call waits for state change to complete
-        if let Some(ref p) = self.primary {
-            p.play();
-        }
-        if let Some(ref s) = self.secondary {
-            s.play();
-        }
-    }
-
-    pub fn stop(&self) {
-        // Stop both elements, each waits for state change
-        if let Some(ref p) = self.primary {
-            p.stop();
-        }
-        if let Some(ref s) = self.secondary {
-            s.stop();
-        }
-    }
-
-    /// Update the channel with a new metric value (0.0 to 1.0)
-    pub fn update(&mut self, target_value: f64) {
+    /// Update the channel with a new metric value (0.0 to 1.0), `elapsed` after the
+    /// previous update
+    pub fn update(&mut self, target_value: f64, elapsed: Duration) {
         let target = target_value.clamp(0.0, 1.0);
 
         // Smooth transition
-        self.current_value += (target - self.current_value) * self.transition_speed;
+        self.current_value = self.smoothing.step(self.current_value, target, elapsed);
+
+        // Shape the smoothed value by the channel's response
+        let level = self.response.apply(self.current_value);
 
         match self.mode {
             SoundMode::Disabled => {
@@ -469,20 +496,18 @@ impl AudioChannel {
             SoundMode::Volume => {
                 // Volume mode: modulate volume based on metric
                 if let Some(ref p) = self.primary {
-                    p.set_volume(self.current_value * self.master_volume);
+                    p.set_volume(level * self.master_volume);
 
                     // Apply frequency fluctuation if enabled
                     if self.frequency_fluctuation {
-                        // Map 0-1 to pitch range 0.8-1.2
-                        let rate = 0.8 + self.current_value * 0.4;
-                        p.set_rate(rate);
+                        p.set_rate(self.pitch.apply(self.current_value));
                     }
                 }
             }
             SoundMode::Fade => {
                 // Fade mode: crossfade between idle and active sounds
-                let idle_vol = (1.0 - self.current_value) * self.master_volume;
-                let active_vol = self.current_value * self.master_volume;
+                let idle_vol = (1.0 - level) * self.master_volume;
+                let active_vol = level * self.master_volume;
 
                 if let Some(ref p) = self.primary {
                     p.set_volume(idle_vol);
@@ -494,8 +519,7 @@ impl AudioChannel {
                 // Apply frequency fluctuation to active sound if enabled
                 if self.frequency_fluctuation {
                     if let Some(ref s) = self.secondary {
-                        let rate = 0.8 + self.current_value * 0.4;
-                        s.set_rate(rate);
+                        s.set_rate(self.pitch.apply(self.current_value));
                     }
                 }
             }
@@ -506,6 +530,16 @@ impl AudioChannel {
         self.master_volume = volume.clamp(0.0, 1.0);
     }
 
+    /// Set how the smoothed value maps onto volume
+    pub fn set_response(&mut self, response: Response) {
+        self.response = response;
+    }
+
+    /// Set how the smoothed value maps onto pitch
+    pub fn set_pitch(&mut self, pitch: PitchResponse) {
+        self.pitch = pitch;
+    }
+
     pub fn reset(&mut self) {
         self.current_value = 0.0;
     }
@@ -527,8 +561,12 @@ pub enum CpuPlayback {
 pub struct AudioMixer {
     /// CPU playback - either averaged or per-core
     pub cpu_playback: Option<CpuPlayback>,
-    pub ram_channel: Option<AudioChannel>,
-    pub disk_channel: Option<AudioChannel>,
+    /// Per-device disk voices (replaces the disk channels when enabled)
+    pub disk_device_player: Option<PerCoreCpuPlayer>,
+    /// Channels keyed by the ID of the metric they follow
+    pub metric_channels: Vec<(String, AudioChannel)>,
+    /// One-shot sounds keyed by the ID of the event metric that fires them
+    pub triggers: Vec<(String, OneShotSound)>,
     master_volume: f64,
 }
 
@@ -536,40 +574,13 @@ impl AudioMixer {
     pub fn new() -> Self {
         Self {
             cpu_playback: None,
-            ram_channel: None,
-            disk_channel: None,
+            disk_device_player: None,
+            metric_channels: Vec::new(),
+            triggers: Vec::new(),
             master_volume: 1.0,
         }
     }
 
-    pub fn play_all(&self) {
-        match &self.cpu_playback {
-            Some(CpuPlayback::Averaged(ch)) => ch.play(),
-            Some(CpuPlayback::PerCore(player)) => player.play(),
-            None => {}
-        }
-        if let Some(ref ch) = self.ram_channel {
-            ch.play();
-        }
-        if let Some(ref ch) = self.disk_channel {
-            ch.play();
-        }
-    }
-
-    pub fn stop_all(&self) {
-        match &self.cpu_playback {
-            Some(CpuPlayback::Averaged(ch)) => ch.stop(),
-            Some(CpuPlayback::PerCore(player)) => player.stop(),
-            None => {}
-        }
-        if let Some(ref ch) = self.ram_channel {
-            ch.stop();
-        }
-        if let Some(ref ch) = self.disk_channel {
-            ch.stop();
-        }
-    }
-
     pub fn set_master_volume(&mut self, volume: f64) {
         self.master_volume = volume.clamp(0.0, 1.0);
         match &mut self.cpu_playback {
@@ -577,19 +588,30 @@ impl AudioMixer {
             Some(CpuPlayback::PerCore(player)) => player.set_master_volume(self.master_volume),
             None => {}
         }
-        if let Some(ref mut ch) = self.ram_channel {
-            ch.set_master_volume(self.master_volume);
+        if let Some(ref mut player) = self.disk_device_player {
+            player.set_master_volume(self.master_volume);
         }
-        if let Some(ref mut ch) = self.disk_channel {
+        for (_, ch) in &mut self.metric_channels {
             ch.set_master_volume(self.master_volume);
         }
     }
 
+    /// Play the one-shot sounds whose event metric has fired
+    pub fn play_triggers(&self, fired: impl Fn(&str) -> bool) {
+        for (metric, sound) in &self.triggers {
+            if fired(metric) {
+                sound.trigger(self.master_volume);
+            }
+        }
+    }
+
+    /// Drop every sound. They are branches of the output's pipeline, which is started
+    /// and stopped as a whole.
     pub fn clear(&mut self) {
-        self.stop_all();
         self.cpu_playback = None;
-        self.ram_channel = None;
-        self.disk_channel = None;
+        self.disk_device_player = None;
+        self.metric_channels.clear();
+        self.triggers.clear();
     }
 }
 
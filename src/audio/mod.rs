@@ -1,6 +1,11 @@
+mod cache;
+mod device;
 mod engine;
 mod mixer;
+mod pcm;
 mod pitch;
+mod render;
 
 pub use engine::AudioEngine;
 pub use pitch::GranularPitchElement;
+pub use render::Renderer;
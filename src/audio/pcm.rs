@@ -0,0 +1,377 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_audio as gst_audio;
+use std::f64::consts::FRAC_PI_2;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Sample rate sounds are decoded to, and mixed at
+pub(super) const RATE: u64 = 48_000;
+
+/// How long to wait for a pipeline before checking it for errors
+pub(super) const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long decoding one file may take before it is given up on
+const DECODE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A sound file decoded to interleaved 32-bit float samples at `RATE`
+pub(super) struct Pcm {
+    samples: Vec<f32>,
+    channels: usize,
+}
+
+impl Pcm {
+    pub(super) fn new(samples: Vec<f32>, channels: usize) -> Self {
+        Self { samples, channels }
+    }
+
+    fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1)
+    }
+
+    /// Size of one frame of samples, in bytes
+    pub(super) fn frame_bytes(&self) -> usize {
+        self.channels.max(1) * std::mem::size_of::<f32>()
+    }
+
+    /// Memory taken by the samples
+    pub(super) fn size_bytes(&self) -> usize {
+        self.samples.len() * std::mem::size_of::<f32>()
+    }
+
+    /// An appsrc for pushing this sound into a pipeline
+    pub(super) fn appsrc(&self) -> Result<gst::Element, gst::glib::BoolError> {
+        let caps = gst::Caps::builder("audio/x-raw")
+            .field("format", gst_audio::AUDIO_FORMAT_F32.to_str())
+            .field("layout", "interleaved")
+            .field("rate", RATE as i32)
+            .field("channels", self.channels as i32)
+            .build();
+        gst::ElementFactory::make("appsrc")
+            .property("caps", &caps)
+            .property("format", gst::Format::Time)
+            .property("is-live", false)
+            .build()
+    }
+
+    /// The sound as a loop whose end crossfades into its start over `crossfade`, for
+    /// files without clean loop points. The loop is shorter by the crossfade.
+    pub(super) fn looped(self: &Arc<Self>, crossfade: Duration) -> Arc<Self> {
+        let channels = self.channels.max(1);
+        let frames = self.frames();
+        let fade = ((RATE as f64 * crossfade.as_secs_f64()) as usize).min(frames / 2);
+        if fade == 0 {
+            return Arc::clone(self);
+        }
+
+        let mut samples = self.samples[..(frames - fade) * channels].to_vec();
+        for i in 0..fade {
+            // Equal power, so the level holds steady through the fade
+            let t = (i as f64 + 0.5) / fade as f64;
+            let fade_in = (t * FRAC_PI_2).sin() as f32;
+            let fade_out = (t * FRAC_PI_2).cos() as f32;
+            let tail = frames - fade + i;
+            for channel in 0..channels {
+                samples[i * channels + channel] = self.samples[i * channels + channel] * fade_in
+                    + self.samples[tail * channels + channel] * fade_out;
+            }
+        }
+
+        Arc::new(Self {
+            samples,
+            channels: self.channels,
+        })
+    }
+}
+
+/// How a feed plays its sound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum FeedMode {
+    /// Over and over, like the looping channels
+    Loop,
+    /// Once each time it is triggered, silent otherwise
+    OneShot,
+}
+
+/// A decoded sound pushed into a pipeline through an appsrc.
+/// Buffers are timestamped back to back, so loops play without a gap.
+pub(super) struct Feed {
+    pub(super) appsrc: gst::Element,
+    playhead: Playhead,
+    /// Frames pushed since the start
+    pushed: u64,
+}
+
+impl Feed {
+    pub(super) fn new(appsrc: gst::Element, pcm: Arc<Pcm>, mode: FeedMode) -> Self {
+        Self {
+            appsrc,
+            playhead: Playhead::new(pcm, mode),
+            pushed: 0,
+        }
+    }
+
+    /// Start a one-shot from the beginning of its sound
+    pub(super) fn trigger(&mut self) {
+        self.playhead.cursor = Some(0);
+    }
+
+    /// Go back to the start, for a pipeline that starts over
+    pub(super) fn rewind(&mut self) {
+        self.playhead = Playhead::new(Arc::clone(&self.playhead.pcm), self.playhead.mode);
+        self.pushed = 0;
+    }
+
+    /// Push the next `frames` frames into the appsrc
+    pub(super) fn push(&mut self, frames: usize) -> gst::FlowReturn {
+        let start = frame_time(self.pushed);
+        self.pushed += frames as u64;
+        let end = frame_time(self.pushed);
+
+        let mut buffer = gst::Buffer::from_mut_slice(self.playhead.next_chunk(frames));
+        if let Some(buffer) = buffer.get_mut() {
+            buffer.set_pts(start);
+            buffer.set_duration(end - start);
+        }
+        self.appsrc
+            .emit_by_name::<gst::FlowReturn>("push-buffer", &[&buffer])
+    }
+}
+
+/// Where a feed is in its sound
+struct Playhead {
+    pcm: Arc<Pcm>,
+    mode: FeedMode,
+    /// Next frame of the sound to push (None while a one-shot is silent)
+    cursor: Option<usize>,
+}
+
+impl Playhead {
+    fn new(pcm: Arc<Pcm>, mode: FeedMode) -> Self {
+        Self {
+            pcm,
+            mode,
+            cursor: match mode {
+                FeedMode::Loop => Some(0),
+                FeedMode::OneShot => None,
+            },
+        }
+    }
+
+    /// The next `frames` frames of the sound, as little-endian bytes
+    fn next_chunk(&mut self, frames: usize) -> Vec<u8> {
+        let channels = self.pcm.channels;
+        let mut bytes = Vec::with_capacity(frames * channels * 4);
+
+        for _ in 0..frames {
+            let frame = match self.cursor {
+                Some(cursor) if cursor < self.pcm.frames() => {
+                    self.cursor = Some(cursor + 1);
+                    Some(cursor)
+                }
+                Some(_) if self.mode == FeedMode::Loop && self.pcm.frames() > 0 => {
+                    self.cursor = Some(1);
+                    Some(0)
+                }
+                _ => {
+                    self.cursor = None;
+                    None
+                }
+            };
+
+            for channel in 0..channels {
+                let sample = frame
+                    .map(|f| self.pcm.samples[f * channels + channel])
+                    .unwrap_or(0.0);
+                bytes.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+}
+
+/// Time of a frame from the start
+pub(super) fn frame_time(frame: u64) -> gst::ClockTime {
+    gst::ClockTime::from_nseconds(frame * 1_000_000_000 / RATE)
+}
+
+/// Decode a whole sound file to float samples at `RATE`
+pub(super) fn decode_file(file_path: &Path) -> Result<Pcm, gst::glib::BoolError> {
+    let abs_path = if file_path.is_absolute() {
+        file_path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .unwrap_or_default()
+            .join(file_path)
+    };
+
+    // Escapes spaces, '#' and the like, which a plain file:// prefix would not
+    let uri = gst::glib::filename_to_uri(&abs_path, None)
+        .map_err(|e| gst::glib::bool_error!("Invalid path {}: {}", abs_path.display(), e))?;
+
+    let pipeline = gst::Pipeline::new();
+    let source = gst::ElementFactory::make("uridecodebin")
+        .property("uri", &uri)
+        .build()?;
+    let convert = gst::ElementFactory::make("audioconvert").build()?;
+    let resample = gst::ElementFactory::make("audioresample").build()?;
+    let capsfilter = gst::ElementFactory::make("capsfilter")
+        .property(
+            "caps",
+            gst::Caps::builder("audio/x-raw")
+                .field("format", gst_audio::AUDIO_FORMAT_F32.to_str())
+                .field("layout", "interleaved")
+                .field("rate", RATE as i32)
+                .build(),
+        )
+        .build()?;
+    let sink = gst::ElementFactory::make("appsink")
+        .property("sync", false)
+        .build()?;
+
+    pipeline.add_many([&source, &convert, &resample, &capsfilter, &sink])?;
+    gst::Element::link_many([&convert, &resample, &capsfilter, &sink])?;
+
+    let convert_weak = convert.downgrade();
+    source.connect_pad_added(move |_, src_pad| {
+        if let Some(convert) = convert_weak.upgrade() {
+            if let Some(sink_pad) = convert.static_pad("sink") {
+                if !sink_pad.is_linked() {
+                    let _ = src_pad.link(&sink_pad);
+                }
+            }
+        }
+    });
+
+    let result = pull_samples(&pipeline, &sink);
+    let _ = pipeline.set_state(gst::State::Null);
+    result.map_err(|e| gst::glib::bool_error!("Failed to decode {}: {}", file_path.display(), e))
+}
+
+/// Play a decoding pipeline to the end, collecting what reaches its appsink
+fn pull_samples(pipeline: &gst::Pipeline, sink: &gst::Element) -> Result<Pcm, String> {
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|e| e.to_string())?;
+
+    let mut pcm = Pcm::new(Vec::new(), 1);
+    let deadline = Instant::now() + DECODE_TIMEOUT;
+
+    loop {
+        if Instant::now() >= deadline {
+            return Err(format!("timed out after {} seconds", DECODE_TIMEOUT.as_secs()));
+        }
+
+        let sample = sink.emit_by_name::<Option<gst::Sample>>(
+            "try-pull-sample",
+            &[&(POLL_INTERVAL.as_nanos() as u64)],
+        );
+
+        let Some(sample) = sample else {
+            if sink.property::<bool>("eos") {
+                return Ok(pcm);
+            }
+            if let Some(message) = pipeline.bus().and_then(|b| b.pop_filtered(&[gst::MessageType::Error])) {
+                if let gst::MessageView::Error(err) = message.view() {
+                    return Err(err.error().to_string());
+                }
+            }
+            continue;
+        };
+
+        if let Some(info) = sample.caps().and_then(|c| gst_audio::AudioInfo::from_caps(c).ok()) {
+            pcm.channels = info.channels() as usize;
+        }
+        if let Some(buffer) = sample.buffer() {
+            let map = buffer.map_readable().map_err(|e| e.to_string())?;
+            pcm.samples.extend(
+                map.as_slice()
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stereo sound whose left channel counts frames and right channel counts down
+    fn ramp(frames: usize) -> Arc<Pcm> {
+        let samples = (0..frames)
+            .flat_map(|f| [f as f32, -(f as f32)])
+            .collect();
+        Arc::new(Pcm::new(samples, 2))
+    }
+
+    /// Duration of `frames` frames at `RATE`
+    fn frames_long(frames: u64) -> Duration {
+        Duration::from_nanos(frames * 1_000_000_000 / RATE)
+    }
+
+    fn floats(bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn looped_without_crossfade_is_the_same_sound() {
+        let pcm = ramp(100);
+        assert!(Arc::ptr_eq(&pcm.looped(Duration::ZERO), &pcm));
+    }
+
+    #[test]
+    fn looped_is_shorter_by_the_crossfade() {
+        let pcm = ramp(2000);
+        let looped = pcm.looped(frames_long(480));
+        assert_eq!(looped.frames(), 2000 - 480);
+        assert_eq!(looped.channels, 2);
+    }
+
+    #[test]
+    fn crossfade_is_at_most_half_the_sound() {
+        let looped = ramp(100).looped(Duration::from_secs(1));
+        assert_eq!(looped.frames(), 50);
+    }
+
+    #[test]
+    fn loop_start_is_an_equal_power_mix_of_start_and_end() {
+        let pcm = ramp(2000);
+        let fade = 480;
+        let looped = pcm.looped(frames_long(fade as u64));
+
+        for i in [0, 1, fade / 2, fade - 1] {
+            let t = (i as f64 + 0.5) / fade as f64;
+            let (fade_in, fade_out) = ((t * FRAC_PI_2).sin(), (t * FRAC_PI_2).cos());
+            let tail = (2000 - fade + i) as f64;
+            let left = i as f64 * fade_in + tail * fade_out;
+            assert!((looped.samples[i * 2] as f64 - left).abs() < 1e-2);
+            assert!((looped.samples[i * 2 + 1] as f64 + left).abs() < 1e-2);
+        }
+        // Past the fade the sound is untouched
+        assert_eq!(looped.samples[fade * 2], fade as f32);
+        assert_eq!(looped.samples[(2000 - fade - 1) * 2], (2000 - fade - 1) as f32);
+    }
+
+    #[test]
+    fn looping_feed_wraps_to_the_start_without_a_gap() {
+        let pcm = Arc::new(Pcm::new(vec![1.0, 2.0, 3.0], 1));
+        let mut playhead = Playhead::new(pcm, FeedMode::Loop);
+        assert_eq!(floats(&playhead.next_chunk(4)), [1.0, 2.0, 3.0, 1.0]);
+        assert_eq!(floats(&playhead.next_chunk(4)), [2.0, 3.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn one_shot_feed_plays_once_when_triggered() {
+        let pcm = Arc::new(Pcm::new(vec![1.0, 2.0], 1));
+        let mut playhead = Playhead::new(pcm, FeedMode::OneShot);
+        assert_eq!(floats(&playhead.next_chunk(2)), [0.0, 0.0]);
+        playhead.cursor = Some(0);
+        assert_eq!(floats(&playhead.next_chunk(4)), [1.0, 2.0, 0.0, 0.0]);
+    }
+}
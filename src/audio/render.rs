@@ -0,0 +1,459 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_audio as gst_audio;
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use super::engine::{AudioEngine, AudioEngineError};
+use super::mixer::AudioOutput;
+use super::cache::SampleCache;
+use super::pcm::{frame_time, Feed, FeedMode, POLL_INTERVAL, RATE};
+use crate::monitor::SystemMetrics;
+
+/// How long a step may take to be mixed, or the file to be finalized, before the
+/// render is given up as stuck
+const STALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The pipeline every sound is mixed into when rendering to a file.
+///
+/// Sounds are decoded up front and pushed in steps by `Renderer`, which waits for each
+/// step to be mixed before applying the next metrics, so the result doesn't depend on
+/// how fast the machine is.
+pub struct RenderGraph {
+    pipeline: gst::Pipeline,
+    mixer: gst::Element,
+    feeds: RefCell<Vec<Feed>>,
+    /// Decoded sounds, shared with other pipelines
+    cache: SampleCache,
+    /// How long the end of each loop crossfades into its start
+    loop_crossfade: Cell<Duration>,
+    /// Frames pushed into every feed so far
+    frames_pushed: Cell<u64>,
+    /// End time (ns) of the latest mixed buffer
+    mixed_until: Arc<(Mutex<u64>, Condvar)>,
+}
+
+impl RenderGraph {
+    /// The pipeline sounds are built in
+    pub(super) fn pipeline(&self) -> &gst::Pipeline {
+        &self.pipeline
+    }
+
+    /// Add an appsrc playing `file_path` to the pipeline.
+    /// Returns the element and the feed's index, for triggering one-shots.
+    pub(super) fn add_source(
+        &self,
+        file_path: &Path,
+        mode: FeedMode,
+    ) -> Result<(gst::Element, usize), gst::glib::BoolError> {
+        let mut pcm = self.cache.get(file_path)?;
+        if mode == FeedMode::Loop {
+            pcm = pcm.looped(self.loop_crossfade.get());
+        }
+
+        let appsrc = pcm.appsrc()?;
+        // Unlimited: a step is pushed in one go, never more
+        appsrc.set_property("max-bytes", 0u64);
+        self.pipeline.add(&appsrc)?;
+
+        let mut feeds = self.feeds.borrow_mut();
+        feeds.push(Feed::new(appsrc.clone(), pcm, mode));
+
+        Ok((appsrc, feeds.len() - 1))
+    }
+
+    /// Link the end of a sound's chain into the mix
+    pub(super) fn link_output(&self, last: &gst::Element) -> Result<(), gst::glib::BoolError> {
+        let mixer_pad = self
+            .mixer
+            .request_pad_simple("sink_%u")
+            .ok_or_else(|| gst::glib::bool_error!("Failed to request a mixer pad"))?;
+        let src_pad = last
+            .static_pad("src")
+            .ok_or_else(|| gst::glib::bool_error!("Element has no src pad"))?;
+        src_pad
+            .link(&mixer_pad)
+            .map_err(|e| gst::glib::bool_error!("Failed to link to the mixer: {:?}", e))?;
+        Ok(())
+    }
+
+    /// Start a one-shot feed from the beginning of its sound
+    pub(super) fn trigger(&self, feed: usize) {
+        if let Some(feed) = self.feeds.borrow_mut().get_mut(feed) {
+            feed.trigger();
+        }
+    }
+
+    /// Crossfade the end of loops added from now on into their start
+    pub(super) fn set_loop_crossfade(&self, crossfade: Duration) {
+        self.loop_crossfade.set(crossfade);
+    }
+
+    /// Push the next `frames` frames into every feed. Returns the end time of the step.
+    fn push(&self, frames: usize) -> Result<gst::ClockTime, AudioEngineError> {
+        self.frames_pushed.set(self.frames_pushed.get() + frames as u64);
+        let end = frame_time(self.frames_pushed.get());
+
+        for feed in self.feeds.borrow_mut().iter_mut() {
+            let flow = feed.push(frames);
+            if flow != gst::FlowReturn::Ok {
+                return Err(self.error().unwrap_or_else(|| {
+                    AudioEngineError::RenderFailed(format!("pushing audio failed ({:?})", flow))
+                }));
+            }
+        }
+
+        Ok(end)
+    }
+
+    /// Wait until everything up to `end` has been mixed
+    fn wait_until(&self, end: gst::ClockTime) -> Result<(), AudioEngineError> {
+        let (lock, mixed) = &*self.mixed_until;
+        let mut mixed_until = lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let deadline = Instant::now() + STALL_TIMEOUT;
+
+        while *mixed_until < end.nseconds() {
+            if let Some(error) = self.error() {
+                return Err(error);
+            }
+            if Instant::now() >= deadline {
+                return Err(AudioEngineError::RenderFailed(format!(
+                    "mixing stalled at {}",
+                    gst::ClockTime::from_nseconds(*mixed_until)
+                )));
+            }
+            mixed_until = mixed
+                .wait_timeout(mixed_until, POLL_INTERVAL)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+
+        Ok(())
+    }
+
+    /// End every feed and wait for the file to be finalized
+    fn finish(&self) -> Result<(), AudioEngineError> {
+        for feed in self.feeds.borrow().iter() {
+            let _ = feed.appsrc.emit_by_name::<gst::FlowReturn>("end-of-stream", &[]);
+        }
+
+        let timeout = gst::ClockTime::from_nseconds(STALL_TIMEOUT.as_nanos() as u64);
+        let message = self.pipeline.bus().and_then(|bus| {
+            bus.timed_pop_filtered(timeout, &[gst::MessageType::Eos, gst::MessageType::Error])
+        });
+        let result = match message.as_ref().map(|m| m.view()) {
+            Some(gst::MessageView::Error(err)) => Err(AudioEngineError::RenderFailed(format!(
+                "{} ({:?})",
+                err.error(),
+                err.debug()
+            ))),
+            Some(_) => Ok(()),
+            None => Err(AudioEngineError::RenderFailed(
+                "timed out finalizing the file".to_string(),
+            )),
+        };
+
+        let _ = self.pipeline.set_state(gst::State::Null);
+        result
+    }
+
+    /// The first error the pipeline reported, if any
+    fn error(&self) -> Option<AudioEngineError> {
+        let message = self.pipeline.bus()?.pop_filtered(&[gst::MessageType::Error])?;
+        match message.view() {
+            gst::MessageView::Error(err) => Some(AudioEngineError::RenderFailed(format!(
+                "{} ({:?})",
+                err.error(),
+                err.debug()
+            ))),
+            _ => None,
+        }
+    }
+}
+
+/// Renders a pack's audio for a trace of metrics into a WAV or Ogg Vorbis file.
+///
+/// The mixer is built exactly as for live playback (see `AudioOutput::Render`) but runs
+/// as fast as the machine allows, with the metrics applied once per `step` of audio.
+pub struct Renderer {
+    graph: Rc<RenderGraph>,
+    step: Duration,
+}
+
+impl Renderer {
+    /// Prepare a render into `path`, encoded by its extension (.wav, .ogg or .oga).
+    /// `step` is the time between metric updates, as the update loop's refresh rate.
+    pub fn new(path: &Path, step: Duration) -> Result<Self, AudioEngineError> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let encoders: &[&str] = match extension.as_deref() {
+            Some("wav") => &["wavenc"],
+            Some("ogg") | Some("oga") => &["vorbisenc", "oggmux"],
+            _ => {
+                return Err(AudioEngineError::RenderFailed(format!(
+                    "unsupported file type '{}' (use .wav or .ogg)",
+                    path.display()
+                )))
+            }
+        };
+
+        let pipeline = gst::Pipeline::new();
+        let mixer = gst::ElementFactory::make("audiomixer")
+            // One mixed buffer per step, so each step can be waited for
+            .property("output-buffer-duration", step.as_nanos() as u64)
+            .build()?;
+        let capsfilter = gst::ElementFactory::make("capsfilter")
+            .property(
+                "caps",
+                gst::Caps::builder("audio/x-raw")
+                    .field("format", gst_audio::AUDIO_FORMAT_F32.to_str())
+                    .field("layout", "interleaved")
+                    .field("rate", RATE as i32)
+                    .field("channels", 2i32)
+                    .build(),
+            )
+            .build()?;
+        let convert = gst::ElementFactory::make("audioconvert").build()?;
+        let sink = gst::ElementFactory::make("filesink")
+            .property("location", path.to_string_lossy().to_string())
+            .build()?;
+
+        let mut chain = vec![mixer.clone(), capsfilter, convert];
+        for name in encoders {
+            chain.push(gst::ElementFactory::make(name).build()?);
+        }
+        chain.push(sink);
+        pipeline.add_many(&chain)?;
+        gst::Element::link_many(&chain)?;
+
+        // Note how far the mix has got, to wait for each step
+        let mixed_until = Arc::new((Mutex::new(0u64), Condvar::new()));
+        let probe_mixed = Arc::clone(&mixed_until);
+        if let Some(pad) = mixer.static_pad("src") {
+            pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+                if let Some(gst::PadProbeData::Buffer(ref buffer)) = info.data {
+                    if let Some(pts) = buffer.pts() {
+                        let end = pts + buffer.duration().unwrap_or(gst::ClockTime::ZERO);
+                        let (lock, mixed) = &*probe_mixed;
+                        let mut until = lock.lock().unwrap_or_else(PoisonError::into_inner);
+                        *until = (*until).max(end.nseconds());
+                        mixed.notify_all();
+                    }
+                }
+                gst::PadProbeReturn::Ok
+            });
+        }
+
+        // A render decodes its own sounds, which are gone once it exits
+        let cache = SampleCache::default();
+
+        Ok(Self {
+            graph: Rc::new(RenderGraph {
+                pipeline,
+                mixer,
+                feeds: RefCell::new(Vec::new()),
+                cache,
+                loop_crossfade: Cell::new(Duration::ZERO),
+                frames_pushed: Cell::new(0),
+                mixed_until,
+            }),
+            step,
+        })
+    }
+
+    /// Where an engine should build its sounds for this render
+    pub fn output(&self) -> AudioOutput {
+        AudioOutput::Render(Rc::clone(&self.graph))
+    }
+
+    /// Render `duration` of audio, updating `engine` with the metrics `metrics_at` gives
+    /// for every step. The engine must have loaded its pack with this render's output.
+    pub fn render(
+        &self,
+        engine: &mut AudioEngine,
+        duration: Duration,
+        mut metrics_at: impl FnMut(Duration) -> SystemMetrics,
+    ) -> Result<(), AudioEngineError> {
+        if self.graph.feeds.borrow().is_empty() {
+            return Err(AudioEngineError::RenderFailed("the pack has no sounds".to_string()));
+        }
+
+        let frames = (RATE as f64 * self.step.as_secs_f64()).round().max(1.0) as usize;
+        let steps = (duration.as_secs_f64() / self.step.as_secs_f64()).ceil() as u32;
+
+        if self.graph.pipeline.set_state(gst::State::Playing).is_err() {
+            let _ = self.graph.pipeline.set_state(gst::State::Null);
+            return Err(self.graph.error().unwrap_or_else(|| {
+                AudioEngineError::RenderFailed("failed to start the render pipeline".to_string())
+            }));
+        }
+
+        for step in 0..steps {
+            engine.update(&metrics_at(self.step * step), self.step);
+            let end = self.graph.push(frames)?;
+            self.graph.wait_until(end)?;
+        }
+
+        self.graph.finish()
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        let _ = self.graph.pipeline.set_state(gst::State::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::{Metric, MetricValue};
+    use crate::pack::PackLoader;
+    use std::f64::consts::TAU;
+    use std::fs;
+
+    /// Elements a render of a WAV sound needs
+    const ELEMENTS: &[&str] = &[
+        "appsrc",
+        "appsink",
+        "uridecodebin",
+        "wavparse",
+        "audiomixer",
+        "audioconvert",
+        "audioresample",
+        "audiopanorama",
+        "volume",
+        "capsfilter",
+        "wavenc",
+        "filesink",
+    ];
+
+    const STEP: Duration = Duration::from_millis(250);
+
+    /// Write one second of a 440 Hz tone as a 16-bit mono WAV file
+    fn write_tone(path: &Path) {
+        let samples: Vec<i16> = (0..RATE)
+            .map(|i| ((i as f64 * 440.0 * TAU / RATE as f64).sin() * 16_000.0) as i16)
+            .collect();
+        let data_size = (samples.len() * 2) as u32;
+
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+        wav.extend_from_slice(&(RATE as u32).to_le_bytes());
+        wav.extend_from_slice(&(RATE as u32 * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_size.to_le_bytes());
+        for sample in samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        fs::write(path, wav).unwrap();
+    }
+
+    /// Read a rendered WAV file into frames of samples between -1.0 and 1.0
+    fn read_wav(path: &Path) -> Vec<Vec<f64>> {
+        let bytes = fs::read(path).unwrap();
+        let mut format = (0u16, 0usize, 0usize);
+        let mut data: &[u8] = &[];
+
+        let mut offset = 12;
+        while offset + 8 <= bytes.len() {
+            let id = &bytes[offset..offset + 4];
+            let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+            let chunk = &bytes[offset + 8..(offset + 8 + size).min(bytes.len())];
+            match id {
+                b"fmt " => {
+                    let tag = u16::from_le_bytes([chunk[0], chunk[1]]);
+                    let channels = u16::from_le_bytes([chunk[2], chunk[3]]) as usize;
+                    let bits = u16::from_le_bytes([chunk[14], chunk[15]]) as usize;
+                    format = (tag, channels, bits);
+                }
+                b"data" => data = chunk,
+                _ => {}
+            }
+            offset += 8 + size + size % 2;
+        }
+
+        let (tag, channels, bits) = format;
+        let sample = |b: &[u8]| match (tag, bits) {
+            (3, 32) => f32::from_le_bytes(b.try_into().unwrap()) as f64,
+            (3, 64) => f64::from_le_bytes(b.try_into().unwrap()),
+            (_, 16) => i16::from_le_bytes(b.try_into().unwrap()) as f64 / 32_768.0,
+            (_, 32) => i32::from_le_bytes(b.try_into().unwrap()) as f64 / 2_147_483_648.0,
+            _ => panic!("unexpected WAV format {} with {} bits", tag, bits),
+        };
+        data.chunks_exact(channels * bits / 8)
+            .map(|frame| frame.chunks_exact(bits / 8).map(sample).collect())
+            .collect()
+    }
+
+    /// Loudest sample between two times
+    fn peak(frames: &[Vec<f64>], from: f64, to: f64) -> f64 {
+        let at = |seconds: f64| ((seconds * RATE as f64) as usize).min(frames.len());
+        frames[at(from)..at(to)]
+            .iter()
+            .flatten()
+            .fold(0.0, |peak: f64, s| peak.max(s.abs()))
+    }
+
+    #[test]
+    fn renders_a_trace_step_by_step() {
+        let missing: Vec<&str> = match gst::init() {
+            Ok(()) => ELEMENTS
+                .iter()
+                .copied()
+                .filter(|name| gst::ElementFactory::find(name).is_none())
+                .collect(),
+            Err(_) => vec!["gstreamer"],
+        };
+        if !missing.is_empty() {
+            eprintln!("Skipping render test, missing {}", missing.join(", "));
+            return;
+        }
+
+        // A pack with only a RAM sound, following its metric without smoothing
+        let dir = tempfile::tempdir().unwrap();
+        let pack_dir = dir.path().join("tone");
+        fs::create_dir(&pack_dir).unwrap();
+        fs::write(
+            pack_dir.join("prefs.ini"),
+            "[soundpack]\nUseAverages=1\nAttackTime=0\nReleaseTime=0\n",
+        )
+        .unwrap();
+        write_tone(&pack_dir.join("RAM.wav"));
+        let pack = PackLoader::new(dir.path()).load_pack(&pack_dir).unwrap();
+
+        // Silent for the first second, then at full level for one more
+        let output = dir.path().join("render.wav");
+        let renderer = Renderer::new(&output, STEP).unwrap();
+        let mut engine = AudioEngine::new().unwrap();
+        engine.set_output(renderer.output());
+        engine.load_pack(pack, 1, 0).unwrap();
+        renderer
+            .render(&mut engine, Duration::from_secs(2), |time| {
+                let level = if time < Duration::from_secs(1) { 0.0 } else { 1.0 };
+                let mut metrics = SystemMetrics::default();
+                metrics.insert(vec![Metric::new("memory.usage", MetricValue::new(level))]);
+                metrics
+            })
+            .unwrap();
+
+        let frames = read_wav(&output);
+        let step_frames = RATE as f64 * STEP.as_secs_f64();
+        assert!((frames.len() as f64 - 2.0 * RATE as f64).abs() <= step_frames);
+        assert!(frames.iter().all(|frame| frame.len() == 2));
+        assert!(peak(&frames, 0.0, 0.9) < 1e-3);
+        assert!(peak(&frames, 1.1, 2.0) > 0.1);
+    }
+}
@@ -0,0 +1,307 @@
+use ini::Ini;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::monitor::{
+    CgroupMonitor, CommandConfig, DemoPattern, DeviceFilter, ProcessSelector, PushConfig, ScrapeConfig,
+    SeriesSelector, TemperatureConfig,
+};
+use crate::pack::ResponseOverride;
+
+/// User settings (parsed from ~/.config/charm-linux/config.ini).
+/// Command-line options are applied on top of these.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    /// Which disk devices to monitor
+    pub disk_filter: DeviceFilter,
+    /// Give each monitored disk device its own panned voice
+    pub disk_per_device: bool,
+    /// Which temperature sensor to follow and its idle-critical range
+    pub temperature: TemperatureConfig,
+    /// cgroup v2 directory whose CPU, memory and IO replace the host-wide figures
+    pub cgroup: Option<PathBuf>,
+    /// Processes whose combined activity drives the Process channel
+    pub process: ProcessSelector,
+    /// Shell commands whose output is published as `command.<name>` metrics
+    pub commands: Vec<CommandConfig>,
+    /// Prometheus endpoints whose series are published as `prometheus.<name>` metrics
+    pub scrapes: Vec<ScrapeConfig>,
+    /// Socket and named pipe other programs push `push.<name>` metrics into
+    pub push: PushConfig,
+    /// Synthetic load that replaces the live metrics, for auditioning packs
+    pub demo: Option<DemoPattern>,
+    /// Changes to channel responses (curve, floor and ceiling) applied to every pack
+    pub responses: Vec<ResponseOverride>,
+}
+
+impl Settings {
+    /// Path of the user config file, if a home directory is known
+    pub fn config_path() -> Option<PathBuf> {
+        directories::BaseDirs::new().map(|dirs| dirs.config_dir().join("charm-linux/config.ini"))
+    }
+
+    /// Load settings from the user config file, falling back to defaults
+    pub fn load() -> Self {
+        match Self::config_path() {
+            Some(path) if path.exists() => Self::load_from(&path).unwrap_or_else(|e| {
+                eprintln!("Warning: Failed to read {}: {}", path.display(), e);
+                Self::default()
+            }),
+            _ => Self::default(),
+        }
+    }
+
+    /// Load settings from a specific config file
+    pub fn load_from(path: &Path) -> Result<Self, ini::Error> {
+        // Backslashes are kept as written, since command lines and patterns rely on them
+        let ini = Ini::load_from_file_noescape(path)?;
+        let mut settings = Self::default();
+
+        if let Some(section) = ini.section(Some("disk")) {
+            settings.disk_filter.include = setting(section, "Include").map(parse_list).unwrap_or_default();
+            settings.disk_filter.exclude = setting(section, "Exclude").map(parse_list).unwrap_or_default();
+            settings.disk_per_device = setting(section, "PerDeviceVoices")
+                .and_then(|v| v.parse().ok())
+                .map(|v: i32| v != 0)
+                .unwrap_or(false);
+        }
+
+        if let Some(section) = ini.section(Some("temperature")) {
+            settings.temperature.sensor = setting(section, "Sensor").map(|s| s.to_string());
+            if let Some(idle) = setting(section, "IdleTemp").and_then(|v| v.parse().ok()) {
+                settings.temperature.idle_celsius = idle;
+            }
+            settings.temperature.critical_celsius =
+                setting(section, "CriticalTemp").and_then(|v| v.parse().ok());
+        }
+
+        if let Some(spec) = ini.section(Some("cgroup")).and_then(|s| setting(s, "Path")) {
+            settings.cgroup = CgroupMonitor::resolve(spec);
+            if settings.cgroup.is_none() {
+                eprintln!("Warning: cgroup '{}' not found, monitoring the whole system", spec);
+            }
+        }
+
+        if let Some(section) = ini.section(Some("process")) {
+            settings.process.names = setting(section, "Names").map(parse_list).unwrap_or_default();
+            settings.process.pids = setting(section, "Pids").map(parse_pids).unwrap_or_default();
+            settings.process.parents = setting(section, "Parents").map(parse_pids).unwrap_or_default();
+        }
+
+        if let Some(section) = ini.section(Some("push")) {
+            settings.push.socket = setting(section, "Socket")
+                .and_then(|v| parse_endpoint(v, PushConfig::default_socket));
+            settings.push.fifo = setting(section, "Fifo")
+                .and_then(|v| parse_endpoint(v, PushConfig::default_fifo));
+            if let Some(stale_after) = setting(section, "StaleAfter").and_then(parse_seconds) {
+                settings.push.stale_after = stale_after;
+            }
+            if let Some(decay) = setting(section, "DecayTime").and_then(parse_seconds) {
+                settings.push.decay = decay;
+            }
+        }
+
+        if let Some(section) = ini.section(Some("response")) {
+            settings.responses = ResponseOverride::read_all(section);
+        }
+
+        // One [command.<name>] or [prometheus.<name>] section per metric
+        for (name, section) in ini.iter() {
+            let Some(name) = name else {
+                continue;
+            };
+            if let Some(name) = name.strip_prefix("command.") {
+                settings.commands.extend(parse_command(name, section));
+            } else if let Some(name) = name.strip_prefix("prometheus.") {
+                settings.scrapes.extend(parse_scrape(name, section));
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+/// Parse a [command.<name>] section, warning about anything unusable
+fn parse_command(name: &str, section: &ini::Properties) -> Option<CommandConfig> {
+    let Some(command_line) = section.get("Command") else {
+        eprintln!("Warning: [command.{}] has no Command, ignoring it", name);
+        return None;
+    };
+
+    let mut command = CommandConfig::new(name, command_line);
+
+    if let Some(pattern) = section.get("Pattern") {
+        match Regex::new(pattern) {
+            Ok(regex) => command.pattern = Some(regex),
+            Err(e) => {
+                eprintln!("Warning: [command.{}] has an invalid Pattern: {}", name, e);
+                return None;
+            }
+        }
+    }
+
+    if let Some(min) = setting(section, "Min").and_then(|v| v.parse().ok()) {
+        command.min = min;
+    }
+    if let Some(max) = setting(section, "Max").and_then(|v| v.parse().ok()) {
+        command.max = max;
+    }
+    if let Some(interval) = setting(section, "Interval").and_then(parse_seconds) {
+        command.interval = interval;
+    }
+    if let Some(timeout) = setting(section, "Timeout").and_then(parse_seconds) {
+        command.timeout = timeout;
+    }
+
+    Some(command)
+}
+
+/// Parse a [prometheus.<name>] section, warning about anything unusable
+fn parse_scrape(name: &str, section: &ini::Properties) -> Option<ScrapeConfig> {
+    let (Some(url), Some(series)) = (section.get("Url"), section.get("Series")) else {
+        eprintln!("Warning: [prometheus.{}] needs both Url and Series, ignoring it", name);
+        return None;
+    };
+
+    // ureq is built without TLS, so only plain HTTP endpoints can be scraped
+    if !url.trim().get(..7).is_some_and(|scheme| scheme.eq_ignore_ascii_case("http://")) {
+        eprintln!("Warning: [prometheus.{}] Url must be a plain http:// URL, ignoring it", name);
+        return None;
+    }
+
+    let selector = match SeriesSelector::parse(series) {
+        Ok(selector) => selector,
+        Err(e) => {
+            eprintln!("Warning: [prometheus.{}] has an invalid Series: {}", name, e);
+            return None;
+        }
+    };
+
+    let mut scrape = ScrapeConfig::new(name, url, selector);
+    scrape.rate = setting(section, "Rate")
+        .and_then(|v| v.parse().ok())
+        .map(|v: i32| v != 0)
+        .unwrap_or(false);
+    if let Some(min) = setting(section, "Min").and_then(|v| v.parse().ok()) {
+        scrape.min = min;
+    }
+    if let Some(max) = setting(section, "Max").and_then(|v| v.parse().ok()) {
+        scrape.max = max;
+    }
+    if let Some(interval) = setting(section, "Interval").and_then(parse_seconds) {
+        scrape.interval = interval;
+    }
+    if let Some(timeout) = setting(section, "Timeout").and_then(parse_seconds) {
+        scrape.timeout = timeout;
+    }
+
+    Some(scrape)
+}
+
+/// A setting without a trailing `; comment` or `# comment`.
+/// Not for free-form values such as command lines and patterns, which may contain either.
+fn setting<'a>(section: &'a ini::Properties, key: &str) -> Option<&'a str> {
+    section.get(key).map(strip_comment)
+}
+
+/// A value up to a `;` or `#` that follows whitespace, which starts a trailing comment
+pub(crate) fn strip_comment(value: &str) -> &str {
+    let end = value
+        .char_indices()
+        .find(|&(i, c)| (c == ';' || c == '#') && value[..i].ends_with(char::is_whitespace))
+        .map_or(value.len(), |(i, _)| i);
+    value[..end].trim_end()
+}
+
+/// Parse an endpoint setting: 0 for off, 1 for the default location, or a path
+fn parse_endpoint(value: &str, default: fn() -> Option<PathBuf>) -> Option<PathBuf> {
+    match value.trim() {
+        "" | "0" => None,
+        "1" => {
+            let path = default();
+            if path.is_none() {
+                eprintln!("Warning: XDG_RUNTIME_DIR is not set, give the [push] endpoint a path");
+            }
+            path
+        }
+        path => Some(PathBuf::from(path)),
+    }
+}
+
+/// Parse a positive number of seconds (fractions allowed)
+fn parse_seconds(value: &str) -> Option<Duration> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|s| *s > 0.0 && s.is_finite())
+        .map(Duration::from_secs_f64)
+}
+
+/// Split a comma- or whitespace-separated list value
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Parse a list of process IDs, skipping anything that isn't a number
+fn parse_pids(value: &str) -> Vec<u32> {
+    parse_list(value).iter().filter_map(|s| s.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn load(config: &str) -> Settings {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(config.as_bytes()).unwrap();
+        Settings::load_from(file.path()).unwrap()
+    }
+
+    #[test]
+    fn comments_are_stripped_from_plain_settings() {
+        let settings = load(
+            "[disk]\n\
+             Include=nvme0n1, sda   ; names or globs\n\
+             PerDeviceVoices=1 # one voice each\n\
+             [command.queue]\n\
+             Command=redis-cli llen jobs\n\
+             Max=500                ; full level\n",
+        );
+        assert_eq!(settings.disk_filter.include, ["nvme0n1", "sda"]);
+        assert!(settings.disk_per_device);
+        assert_eq!(settings.commands[0].max, 500.0);
+    }
+
+    #[test]
+    fn only_plain_http_endpoints_are_scraped() {
+        let settings = load(
+            "[prometheus.plain]\n\
+             Url=HTTP://localhost:9100/metrics\n\
+             Series=up\n\
+             [prometheus.tls]\n\
+             Url=https://localhost:9100/metrics\n\
+             Series=up\n",
+        );
+        assert_eq!(settings.scrapes.len(), 1);
+        assert_eq!(settings.scrapes[0].name, "plain");
+    }
+
+    #[test]
+    fn command_lines_and_patterns_are_kept_whole() {
+        let settings = load(
+            "[command.load]\n\
+             Command=uptime ; awk '{print $1} # first'\n\
+             Pattern=(\\d+) ;?\n",
+        );
+        let command = &settings.commands[0];
+        assert_eq!(command.command, "uptime ; awk '{print $1} # first'");
+        assert_eq!(command.pattern.as_ref().unwrap().as_str(), r"(\d+) ;?");
+    }
+}
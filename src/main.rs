@@ -1,27 +1,206 @@
 mod app;
 mod audio;
+mod config;
 mod monitor;
 mod pack;
 mod ui;
 
 use std::cell::RefCell;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use app::App;
+use config::Settings;
+use monitor::{
+    CgroupMonitor, DemoPattern, MetricRecorder, MetricReplay, PushConfig, TemperatureMonitor,
+};
 
 fn print_usage() {
-    eprintln!("Usage: charm-linux [PACK_NAME]");
+    eprintln!("Usage: charm-linux [OPTIONS] [PACK_NAME]");
     eprintln!();
     eprintln!("Arguments:");
     eprintln!("  PACK_NAME    Optional: Start directly with the specified sound pack");
     eprintln!("               (bypasses the selection dialog for headless setups)");
     eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --disk-include PATTERN   Only monitor disk devices matching PATTERN");
+    eprintln!("                           (device name or glob, may be repeated)");
+    eprintln!("  --disk-exclude PATTERN   Never monitor disk devices matching PATTERN");
+    eprintln!("                           (device name or glob, may be repeated)");
+    eprintln!("  --disk-per-device        Give each monitored disk device its own panned voice");
+    eprintln!("  --cgroup PATH|UNIT       Monitor CPU, memory and IO of a cgroup v2 instead of");
+    eprintln!("                           the whole system (path or systemd unit name)");
+    eprintln!("  --watch-process NAME|PID Drive the Process channel from matching processes");
+    eprintln!("                           (name, glob or PID, may be repeated)");
+    eprintln!("  --watch-tree PID         Drive the Process channel from PID and its descendants");
+    eprintln!("  --listen                 Accept pushed metrics on a socket in");
+    eprintln!("                           $XDG_RUNTIME_DIR/charm-linux/");
+    eprintln!("  --demo                   Play a synthetic load instead of monitoring the");
+    eprintln!("                           system, to hear a pack's full range");
+    eprintln!("  --demo-pattern PATTERN   Demo load: ramp (default), sine, random or stagger");
+    eprintln!("  --record FILE            Write metric snapshots to FILE (JSON Lines)");
+    eprintln!("  --replay FILE            Play metrics recorded with --record instead of");
+    eprintln!("                           monitoring the system (loops at the end)");
+    eprintln!("  --speed FACTOR           Replay speed, e.g. 4x (default 1x)");
+    eprintln!("  --render FILE            Render PACK_NAME for the --replay or --demo metrics");
+    eprintln!("                           into FILE (.wav or .ogg) and exit");
+    eprintln!("  --duration SECONDS       Length to render (default: the whole recording,");
+    eprintln!("                           or four demo sweeps)");
+    eprintln!("  --list-sensors           List temperature sensors and exit");
+    eprintln!("  -h, --help               Show this help");
+    eprintln!();
     eprintln!("Examples:");
     eprintln!("  charm-linux              # Show pack selection dialog");
     eprintln!("  charm-linux default      # Start with 'default' pack");
     eprintln!("  charm-linux scifi1       # Start with 'scifi1' pack");
+    eprintln!("  charm-linux --disk-exclude 'sd*' default   # Ignore SATA/USB disks");
+    eprintln!("  charm-linux --watch-process rustc --watch-process 'cc1*' default");
+    eprintln!("  charm-linux --cgroup ci-runner.slice default   # Listen to a CI slice");
+    eprintln!("  charm-linux --replay deploy.jsonl --speed 4x scifi1");
+    eprintln!("  charm-linux --demo --demo-pattern stagger scifi1");
+    eprintln!("  charm-linux --replay build.jsonl --render build.ogg scifi1");
+}
+
+/// Print the available temperature sensors, for use in the [temperature] config section
+fn list_sensors() {
+    let monitor = TemperatureMonitor::default();
+    if monitor.sensors().is_empty() {
+        println!("No temperature sensors found");
+        return;
+    }
+
+    for sensor in monitor.sensors() {
+        let current = sensor
+            .read_celsius()
+            .map(|t| format!("{:.1}°C", t))
+            .unwrap_or_else(|| "unknown".to_string());
+        let critical = sensor
+            .critical_celsius
+            .map(|t| format!(", critical {:.1}°C", t))
+            .unwrap_or_default();
+        println!("{:<16} {}/{}: {}{}", sensor.id, sensor.chip, sensor.label, current, critical);
+    }
+}
+
+/// Parsed command-line arguments
+struct CliArgs {
+    pack_name: Option<String>,
+    settings: Settings,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    speed: f64,
+    render: Option<PathBuf>,
+    duration: Option<Duration>,
+}
+
+/// Parse command-line arguments on top of the settings from the config file
+fn parse_args(args: &[String], mut settings: Settings) -> Result<CliArgs, String> {
+    let mut pack_name = None;
+    let mut record = None;
+    let mut replay = None;
+    let mut speed = 1.0;
+    let mut render = None;
+    let mut duration = None;
+    let mut iter = args.iter().skip(1);
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print_usage();
+                std::process::exit(0);
+            }
+            "--disk-include" => {
+                let pattern = iter.next().ok_or("--disk-include requires a pattern")?;
+                settings.disk_filter.include.push(pattern.clone());
+            }
+            "--disk-exclude" => {
+                let pattern = iter.next().ok_or("--disk-exclude requires a pattern")?;
+                settings.disk_filter.exclude.push(pattern.clone());
+            }
+            "--disk-per-device" => settings.disk_per_device = true,
+            "--cgroup" => {
+                let spec = iter.next().ok_or("--cgroup requires a path or unit name")?;
+                let path = CgroupMonitor::resolve(spec)
+                    .ok_or_else(|| format!("cgroup '{}' not found", spec))?;
+                settings.cgroup = Some(path);
+            }
+            "--watch-process" => {
+                let target = iter.next().ok_or("--watch-process requires a name or PID")?;
+                match target.parse::<u32>() {
+                    Ok(pid) => settings.process.pids.push(pid),
+                    Err(_) => settings.process.names.push(target.clone()),
+                }
+            }
+            "--watch-tree" => {
+                let pid = iter.next().ok_or("--watch-tree requires a PID")?;
+                let pid = pid.parse().map_err(|_| format!("Invalid PID '{}'", pid))?;
+                settings.process.parents.push(pid);
+            }
+            "--listen" => {
+                let path = PushConfig::default_socket().ok_or("--listen requires XDG_RUNTIME_DIR")?;
+                settings.push.socket = Some(path);
+            }
+            "--demo" => {
+                settings.demo.get_or_insert_with(DemoPattern::default);
+            }
+            "--demo-pattern" => {
+                let name = iter.next().ok_or("--demo-pattern requires a pattern")?;
+                let pattern = DemoPattern::from_name(name)
+                    .ok_or_else(|| format!("Unknown demo pattern '{}'", name))?;
+                settings.demo = Some(pattern);
+            }
+            "--record" => {
+                let path = iter.next().ok_or("--record requires a file")?;
+                record = Some(PathBuf::from(path));
+            }
+            "--replay" => {
+                let path = iter.next().ok_or("--replay requires a file")?;
+                replay = Some(PathBuf::from(path));
+            }
+            "--speed" => {
+                let factor = iter.next().ok_or("--speed requires a factor")?;
+                speed = factor
+                    .trim_end_matches(['x', 'X'])
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|s| *s > 0.0 && s.is_finite())
+                    .ok_or_else(|| format!("Invalid speed '{}'", factor))?;
+            }
+            "--render" => {
+                let path = iter.next().ok_or("--render requires a file")?;
+                render = Some(PathBuf::from(path));
+            }
+            "--duration" => {
+                let seconds = iter.next().ok_or("--duration requires a number of seconds")?;
+                let parsed = seconds
+                    .trim_end_matches('s')
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|s| *s > 0.0 && s.is_finite())
+                    .ok_or_else(|| format!("Invalid duration '{}'", seconds))?;
+                duration = Some(Duration::from_secs_f64(parsed));
+            }
+            "--list-sensors" => {
+                list_sensors();
+                std::process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("Unknown option '{}'", arg)),
+            _ if pack_name.is_none() => pack_name = Some(arg.clone()),
+            _ => return Err(format!("Unexpected argument '{}'", arg)),
+        }
+    }
+
+    Ok(CliArgs {
+        pack_name,
+        settings,
+        record,
+        replay,
+        speed,
+        render,
+        duration,
+    })
 }
 
 fn get_packs_directory() -> PathBuf {
@@ -54,20 +233,86 @@ fn get_packs_directory() -> PathBuf {
     local
 }
 
+/// Render a pack to a file and report how it went (no GTK, no audio device)
+fn render_pack(
+    name: &str,
+    path: &Path,
+    duration: Option<Duration>,
+    settings: Settings,
+    replay: Option<MetricReplay>,
+) {
+    let mut app = match App::new(get_packs_directory(), settings) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("Failed to initialize application: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(replay) = replay {
+        app.set_replay(replay);
+    }
+
+    println!("Rendering '{}' to {}", name, path.display());
+    let started = Instant::now();
+    match app.render(name, path, duration) {
+        Ok(rendered) => println!(
+            "Rendered {:.1}s of audio in {:.1}s",
+            rendered.as_secs_f64(),
+            started.elapsed().as_secs_f64()
+        ),
+        Err(e) => {
+            eprintln!("Failed to render: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
 fn main() {
     // Parse command-line arguments
     let args: Vec<String> = env::args().collect();
-    let pack_name = if args.len() > 1 {
-        let arg = &args[1];
-        if arg == "-h" || arg == "--help" {
+    let CliArgs {
+        pack_name,
+        settings,
+        record,
+        replay,
+        speed,
+        render,
+        duration,
+    } = match parse_args(&args, Settings::load()) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!();
             print_usage();
-            std::process::exit(0);
+            std::process::exit(1);
         }
-        Some(arg.clone())
-    } else {
-        None
     };
 
+    // Open recordings before anything else, so a bad path fails fast
+    let recorder = record.map(|path| {
+        MetricRecorder::create(&path).unwrap_or_else(|e| {
+            eprintln!("Failed to create {}: {}", path.display(), e);
+            std::process::exit(1);
+        })
+    });
+    let replay = replay.map(|path| {
+        let replay = MetricReplay::load(&path, speed).unwrap_or_else(|e| {
+            eprintln!("Failed to load {}: {}", path.display(), e);
+            std::process::exit(1);
+        });
+        println!("Replaying metrics from {} at {}x", path.display(), speed);
+        replay
+    });
+
+    if let Some(path) = render {
+        let Some(name) = pack_name else {
+            eprintln!("--render requires a pack name");
+            std::process::exit(1);
+        };
+        render_pack(&name, &path, duration, settings, replay);
+        return;
+    }
+
     // Headless mode: run without GTK when pack name is specified
     let headless = pack_name.is_some();
 
@@ -88,13 +333,19 @@ fn main() {
     println!("Looking for sound packs in: {}", packs_dir.display());
 
     // Create application
-    let app = match App::new(packs_dir) {
+    let app = match App::new(packs_dir, settings) {
         Ok(app) => Rc::new(RefCell::new(app)),
         Err(e) => {
             eprintln!("Failed to initialize application: {}", e);
             std::process::exit(1);
         }
     };
+    if let Some(recorder) = recorder {
+        app.borrow_mut().set_recorder(recorder);
+    }
+    if let Some(replay) = replay {
+        app.borrow_mut().set_replay(replay);
+    }
 
     if headless {
         // Headless mode: start directly with the specified pack
@@ -0,0 +1,415 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use super::{DecayingMax, Metric, MetricSource, MetricValue};
+
+/// Monitors CPU, memory and IO of a single cgroup v2 instead of the whole host
+pub struct CgroupMonitor {
+    path: PathBuf,
+    /// Number of host CPU cores, so per-core voices can follow the cgroup
+    core_count: usize,
+    /// CPUs the cgroup may use (its cpu.max quota, or all cores)
+    cpu_capacity: f64,
+    /// Memory limit used when memory.max is "max"
+    host_memory_bytes: u64,
+    last_usage_usec: u64,
+    last_read_bytes: u64,
+    last_write_bytes: u64,
+    last_time: Instant,
+    /// CPU usage between 0.0 and 1.0 of the cgroup's capacity
+    cpu_level: f64,
+    /// Memory usage between 0.0 and 1.0 of the cgroup's limit
+    memory_level: f64,
+    /// Bytes read per second at last measurement
+    read_level: f64,
+    /// Maximum observed read rate for normalization
+    max_read: DecayingMax,
+    /// Bytes written per second at last measurement
+    write_level: f64,
+    /// Maximum observed write rate for normalization
+    max_write: DecayingMax,
+    /// Bytes read and written per second at last measurement
+    io_level: f64,
+    /// Maximum observed IO rate for normalization
+    max_io: DecayingMax,
+}
+
+impl CgroupMonitor {
+    const CGROUP_ROOT: &'static str = "/sys/fs/cgroup";
+
+    /// Minimum IO threshold (bytes per second) to avoid division by very small numbers
+    const MIN_MAX_IO: f64 = 512_000.0;
+
+    /// Resolve a cgroup given either as a path or a systemd unit name
+    /// (e.g. "ci-runner.slice" or "docker-1234.scope"; ".service" is assumed without a suffix)
+    pub fn resolve(spec: &str) -> Option<PathBuf> {
+        Self::resolve_with_root(spec, Path::new(Self::CGROUP_ROOT))
+    }
+
+    /// Resolve a cgroup against a cgroupfs mounted at `root`
+    pub fn resolve_with_root(spec: &str, root: &Path) -> Option<PathBuf> {
+        if spec.starts_with('/') {
+            let path = PathBuf::from(spec);
+            // Accept paths relative to the cgroup root as well, as printed by systemctl
+            let candidates = [path.clone(), root.join(spec.trim_start_matches('/'))];
+            return candidates
+                .into_iter()
+                .find(|p| p.join("cgroup.controllers").exists());
+        }
+
+        let unit = if spec.contains('.') {
+            spec.to_string()
+        } else {
+            format!("{}.service", spec)
+        };
+
+        Self::find_unit(root, &unit, 0)
+    }
+
+    /// Search the cgroup tree for a directory named after a systemd unit
+    fn find_unit(dir: &Path, unit: &str, depth: usize) -> Option<PathBuf> {
+        const MAX_DEPTH: usize = 8;
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        let mut subdirs: Vec<PathBuf> = fs::read_dir(dir)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+            .map(|e| e.path())
+            .collect();
+        subdirs.sort();
+
+        // Only cgroup v2 directories have cgroup.controllers; v1 hierarchies are skipped
+        if let Some(found) = subdirs.iter().find(|p| {
+            p.file_name().and_then(|n| n.to_str()) == Some(unit)
+                && p.join("cgroup.controllers").exists()
+        }) {
+            return Some(found.clone());
+        }
+
+        subdirs
+            .iter()
+            .find_map(|sub| Self::find_unit(sub, unit, depth + 1))
+    }
+
+    /// Create a monitor for the cgroup at `path`
+    pub fn new(path: impl Into<PathBuf>, core_count: usize, host_memory_bytes: u64) -> Self {
+        let path = path.into();
+        let cpu_capacity = Self::read_cpu_quota(&path).unwrap_or(core_count.max(1) as f64);
+        let (read_bytes, write_bytes) = Self::read_io_stats(&path);
+
+        Self {
+            last_usage_usec: Self::read_cpu_usage_usec(&path),
+            path,
+            core_count,
+            cpu_capacity,
+            host_memory_bytes,
+            last_read_bytes: read_bytes,
+            last_write_bytes: write_bytes,
+            last_time: Instant::now(),
+            cpu_level: 0.0,
+            memory_level: 0.0,
+            read_level: 0.0,
+            max_read: DecayingMax::new(Self::MIN_MAX_IO),
+            write_level: 0.0,
+            max_write: DecayingMax::new(Self::MIN_MAX_IO),
+            io_level: 0.0,
+            max_io: DecayingMax::new(Self::MIN_MAX_IO),
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_time).as_secs_f64();
+
+        let usage_usec = Self::read_cpu_usage_usec(&self.path);
+        let (read_bytes, write_bytes) = Self::read_io_stats(&self.path);
+
+        if elapsed > 0.0 {
+            let cpu_seconds = usage_usec.saturating_sub(self.last_usage_usec) as f64 / 1_000_000.0;
+            self.cpu_level = cpu_seconds / elapsed / self.cpu_capacity;
+
+            let read_delta = read_bytes.saturating_sub(self.last_read_bytes);
+            let write_delta = write_bytes.saturating_sub(self.last_write_bytes);
+
+            // Bytes per second
+            self.read_level = read_delta as f64 / elapsed;
+            self.write_level = write_delta as f64 / elapsed;
+            self.io_level = (read_delta + write_delta) as f64 / elapsed;
+
+            self.max_read.update(self.read_level);
+            self.max_write.update(self.write_level);
+            self.max_io.update(self.io_level);
+        }
+
+        self.memory_level = Self::read_memory_usage(&self.path, self.host_memory_bytes);
+
+        self.last_usage_usec = usage_usec;
+        self.last_read_bytes = read_bytes;
+        self.last_write_bytes = write_bytes;
+        self.last_time = now;
+    }
+
+    /// Returns the cgroup path being monitored
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns CPU usage as a fraction of the CPUs available to the cgroup
+    pub fn cpu_usage(&self) -> MetricValue {
+        MetricValue::new(self.cpu_level)
+    }
+
+    /// Returns memory usage as a fraction of memory.max (or host memory when unlimited)
+    pub fn memory_usage(&self) -> MetricValue {
+        MetricValue::new(self.memory_level)
+    }
+
+    /// Returns IO activity (reads and writes combined) as a normalized value
+    pub fn io_activity(&self) -> MetricValue {
+        self.max_io.normalize(self.io_level)
+    }
+
+    /// Returns IO read activity as a normalized value
+    pub fn read_activity(&self) -> MetricValue {
+        self.max_read.normalize(self.read_level)
+    }
+
+    /// Returns IO write activity as a normalized value
+    pub fn write_activity(&self) -> MetricValue {
+        self.max_write.normalize(self.write_level)
+    }
+
+    /// Read `usage_usec` from cpu.stat
+    fn read_cpu_usage_usec(path: &Path) -> u64 {
+        fs::read_to_string(path.join("cpu.stat"))
+            .ok()
+            .and_then(|content| {
+                content.lines().find_map(|line| match line.split_once(' ') {
+                    Some(("usage_usec", v)) => v.trim().parse().ok(),
+                    _ => None,
+                })
+            })
+            .unwrap_or(0)
+    }
+
+    /// Read the CPU quota from cpu.max ("QUOTA PERIOD" or "max PERIOD") in CPUs
+    fn read_cpu_quota(path: &Path) -> Option<f64> {
+        let content = fs::read_to_string(path.join("cpu.max")).ok()?;
+        let mut fields = content.split_whitespace();
+        let quota: f64 = fields.next()?.parse().ok()?;
+        let period: f64 = fields.next()?.parse().ok()?;
+        (period > 0.0).then_some(quota / period)
+    }
+
+    /// Read memory.current against memory.max
+    fn read_memory_usage(path: &Path, host_memory_bytes: u64) -> f64 {
+        let read = |file: &str| {
+            fs::read_to_string(path.join(file))
+                .ok()
+                .map(|s| s.trim().to_string())
+        };
+
+        let current: u64 = match read("memory.current").and_then(|v| v.parse().ok()) {
+            Some(c) => c,
+            None => return 0.0,
+        };
+
+        // "max" means unlimited - compare against the whole host instead
+        let limit = read("memory.max")
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(host_memory_bytes)
+            .min(host_memory_bytes.max(1));
+
+        if limit == 0 {
+            return 0.0;
+        }
+
+        current as f64 / limit as f64
+    }
+
+    /// Read total bytes read/written from io.stat, e.g.
+    /// `8:0 rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0`
+    fn read_io_stats(path: &Path) -> (u64, u64) {
+        let content = match fs::read_to_string(path.join("io.stat")) {
+            Ok(c) => c,
+            Err(_) => return (0, 0),
+        };
+
+        let mut total_read = 0u64;
+        let mut total_write = 0u64;
+
+        for field in content.split_whitespace() {
+            match field.split_once('=') {
+                Some(("rbytes", v)) => total_read += v.parse::<u64>().unwrap_or(0),
+                Some(("wbytes", v)) => total_write += v.parse::<u64>().unwrap_or(0),
+                _ => {}
+            }
+        }
+
+        (total_read, total_write)
+    }
+}
+
+impl MetricSource for CgroupMonitor {
+    fn name(&self) -> &str {
+        "cgroup"
+    }
+
+    fn refresh(&mut self) {
+        CgroupMonitor::refresh(self);
+    }
+
+    /// Publishes the host-wide CPU, memory and disk IDs, so that a cgroup registered
+    /// after the host monitors takes their place
+    fn values(&self) -> Vec<Metric> {
+        let cpu_usage = self.cpu_usage();
+        let mut values = vec![
+            Metric::new("cpu.average", cpu_usage),
+            Metric::new("memory.usage", self.memory_usage()),
+            Metric::new("disk.activity", self.io_activity()),
+            Metric::new("disk.read", self.read_activity()),
+            Metric::new("disk.write", self.write_activity()),
+        ];
+        // cpu.stat has no per-core breakdown, so every core voice follows the cgroup total
+        for core in 0..self.core_count {
+            values.push(Metric::instance("cpu.core", core.to_string(), cpu_usage));
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::write_files;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    /// A cgroup v2 tree with a CI runner slice holding one service
+    fn cgroupfs() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        write_files(
+            root.path(),
+            &[
+                ("cgroup.controllers", "cpu io memory\n"),
+                ("ci.slice/cgroup.controllers", "cpu io memory\n"),
+                ("ci.slice/runner.service/cgroup.controllers", "cpu io memory\n"),
+                ("ci.slice/runner.service/cpu.max", "200000 100000\n"),
+                ("ci.slice/runner.service/cpu.stat", "usage_usec 5000\nuser_usec 4000\n"),
+                ("ci.slice/runner.service/memory.current", "536870912\n"),
+                ("ci.slice/runner.service/memory.max", "1073741824\n"),
+                (
+                    "ci.slice/runner.service/io.stat",
+                    "8:0 rbytes=1000 wbytes=2000 rios=1 wios=2\n259:0 rbytes=500 wbytes=0 rios=1 wios=0\n",
+                ),
+            ],
+        );
+        root
+    }
+
+    #[test]
+    fn resolves_paths_and_unit_names() {
+        let root = cgroupfs();
+        let runner = root.path().join("ci.slice/runner.service");
+
+        let absolute = runner.to_str().unwrap();
+        assert_eq!(
+            CgroupMonitor::resolve_with_root(absolute, root.path()),
+            Some(runner.clone())
+        );
+        assert_eq!(
+            CgroupMonitor::resolve_with_root("/ci.slice/runner.service", root.path()),
+            Some(runner.clone())
+        );
+        assert_eq!(
+            CgroupMonitor::resolve_with_root("runner", root.path()),
+            Some(runner)
+        );
+        assert_eq!(
+            CgroupMonitor::resolve_with_root("ci.slice", root.path()),
+            Some(root.path().join("ci.slice"))
+        );
+        assert_eq!(
+            CgroupMonitor::resolve_with_root("missing.scope", root.path()),
+            None
+        );
+    }
+
+    #[test]
+    fn cgroup_v1_is_not_resolved() {
+        let root = tempfile::tempdir().unwrap();
+        write_files(
+            root.path(),
+            &[
+                ("cpu/system.slice/runner.service/cpuacct.usage", "5000\n"),
+                (
+                    "memory/system.slice/runner.service/memory.usage_in_bytes",
+                    "4096\n",
+                ),
+            ],
+        );
+
+        assert_eq!(
+            CgroupMonitor::resolve_with_root("runner", root.path()),
+            None
+        );
+        let path = root.path().join("cpu/system.slice/runner.service");
+        assert_eq!(
+            CgroupMonitor::resolve_with_root(path.to_str().unwrap(), root.path()),
+            None
+        );
+    }
+
+    #[test]
+    fn reads_limits_and_counters() {
+        let root = cgroupfs();
+        let runner = root.path().join("ci.slice/runner.service");
+
+        assert_eq!(CgroupMonitor::read_cpu_quota(&runner), Some(2.0));
+        assert_eq!(CgroupMonitor::read_cpu_usage_usec(&runner), 5000);
+        assert_eq!(CgroupMonitor::read_io_stats(&runner), (1500, 2000));
+        assert_eq!(CgroupMonitor::read_memory_usage(&runner, 4 * GIB), 0.5);
+    }
+
+    #[test]
+    fn unlimited_memory_and_cpu_fall_back_to_the_host() {
+        let root = cgroupfs();
+        let runner = root.path().join("ci.slice/runner.service");
+        write_files(
+            &runner,
+            &[("memory.max", "max\n"), ("cpu.max", "max 100000\n")],
+        );
+
+        assert_eq!(CgroupMonitor::read_cpu_quota(&runner), None);
+        assert_eq!(CgroupMonitor::read_memory_usage(&runner, 2 * GIB), 0.25);
+        assert_eq!(CgroupMonitor::new(&runner, 8, 2 * GIB).cpu_capacity, 8.0);
+    }
+
+    #[test]
+    fn publishes_host_metric_ids_for_every_core() {
+        let root = cgroupfs();
+        let mut monitor =
+            CgroupMonitor::new(root.path().join("ci.slice/runner.service"), 4, 4 * GIB);
+        monitor.refresh();
+
+        let values = monitor.values();
+        let memory = values.iter().find(|m| m.id == "memory.usage").unwrap();
+        assert_eq!(memory.value.get(), 0.5);
+        assert_eq!(values.iter().filter(|m| m.id == "cpu.core").count(), 4);
+    }
+
+    #[test]
+    fn missing_files_read_zero() {
+        let root = tempfile::tempdir().unwrap();
+        let mut monitor = CgroupMonitor::new(root.path().join("gone.service"), 2, 4 * GIB);
+        monitor.refresh();
+
+        assert_eq!(monitor.cpu_usage().get(), 0.0);
+        assert_eq!(monitor.memory_usage().get(), 0.0);
+        assert_eq!(monitor.io_activity().get(), 0.0);
+    }
+}
@@ -0,0 +1,203 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use super::poll::{scale, Poller};
+use super::{Metric, MetricSource, MetricValue};
+
+/// First number in the output, used when no pattern is configured
+static NUMBER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?").expect("valid number pattern")
+});
+
+/// A shell command whose output is turned into a metric
+#[derive(Debug, Clone)]
+pub struct CommandConfig {
+    /// Name of the metric, published as `command.<name>`
+    pub name: String,
+    /// Command line, run with `sh -c`
+    pub command: String,
+    /// Pattern that locates the number in the output: its first capture group, or the
+    /// whole match without one. When unset, the first number in the output is used.
+    pub pattern: Option<Regex>,
+    /// Reading that maps to 0.0
+    pub min: f64,
+    /// Reading that maps to 1.0 (may be below `min` to invert the metric)
+    pub max: f64,
+    /// Time between runs
+    pub interval: Duration,
+    /// Time after which a run is killed
+    pub timeout: Duration,
+}
+
+impl CommandConfig {
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    pub fn new(name: &str, command: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            pattern: None,
+            min: 0.0,
+            max: 100.0,
+            interval: Self::DEFAULT_INTERVAL,
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// ID of the metric this command is published as
+    pub fn metric_id(&self) -> String {
+        format!("command.{}", self.name)
+    }
+
+    /// Find the number in a command's output
+    fn parse(&self, output: &str) -> Option<f64> {
+        let pattern = self.pattern.as_ref().unwrap_or(&NUMBER);
+        let captures = pattern.captures(output)?;
+        let text = captures.get(1).or_else(|| captures.get(0))?.as_str();
+        text.trim().parse().ok()
+    }
+}
+
+/// Publishes a number printed by a shell command.
+///
+/// The command runs on its own thread so a slow command never stalls the update loop;
+/// `refresh` only picks up the latest reading. When a run fails, times out or prints
+/// no number, the previous reading is kept.
+pub struct CommandSource {
+    config: CommandConfig,
+    metric_id: String,
+    poller: Poller,
+    level: f64,
+}
+
+impl CommandSource {
+    pub fn new(config: CommandConfig) -> Self {
+        let worker_config = config.clone();
+        let poller = Poller::spawn(
+            format!("command '{}'", config.name),
+            config.interval,
+            move || {
+                let output = run(&worker_config.command, worker_config.timeout)?;
+                match worker_config.parse(&output) {
+                    Some(value) => Ok(Some(value)),
+                    None => Err("no number in output".to_string()),
+                }
+            },
+        );
+
+        Self {
+            metric_id: config.metric_id(),
+            config,
+            poller,
+            level: 0.0,
+        }
+    }
+
+    /// Returns the command's latest reading, normalized between min and max
+    pub fn level(&self) -> MetricValue {
+        MetricValue::new(self.level)
+    }
+}
+
+impl MetricSource for CommandSource {
+    fn name(&self) -> &str {
+        &self.metric_id
+    }
+
+    fn refresh(&mut self) {
+        if let Some(reading) = self.poller.latest() {
+            self.level = scale(reading, self.config.min, self.config.max);
+        }
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        vec![Metric::new(&self.metric_id, self.level())]
+    }
+}
+
+/// Run a command through the shell and return its standard output,
+/// killing it if it doesn't finish within `timeout`
+fn run(command: &str, timeout: Duration) -> Result<String, String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        // Own process group, so a timeout also stops whatever the shell started
+        .process_group(0)
+        .spawn()
+        .map_err(|e| format!("failed to start: {}", e))?;
+
+    // Read on another thread so a full pipe can't keep the command from finishing
+    let mut stdout = child.stdout.take().ok_or("no output pipe")?;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stdout.read_to_string(&mut output);
+        let _ = sender.send(output);
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(output) => {
+            let _ = child.wait();
+            Ok(output)
+        }
+        Err(_) => {
+            // SAFETY: kill() has no memory safety requirements; the group was created above
+            unsafe {
+                libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+            }
+            let _ = child.wait();
+            Err(format!("timed out after {:.1}s", timeout.as_secs_f64()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(pattern: Option<&str>) -> CommandConfig {
+        let mut config = CommandConfig::new("test", "true");
+        config.pattern = pattern.map(|p| Regex::new(p).unwrap());
+        config
+    }
+
+    #[test]
+    fn default_pattern_keeps_the_sign() {
+        assert_eq!(config(None).parse("lag: -5 s"), Some(-5.0));
+        assert_eq!(config(None).parse("+2.5"), Some(2.5));
+    }
+
+    #[test]
+    fn default_pattern_reads_exponents() {
+        assert_eq!(config(None).parse("1.5e3 requests"), Some(1500.0));
+        assert_eq!(config(None).parse("rate 2E-2"), Some(0.02));
+        assert_eq!(config(None).parse(".5"), Some(0.5));
+    }
+
+    #[test]
+    fn default_pattern_without_a_number() {
+        assert_eq!(config(None).parse("no digits here"), None);
+    }
+
+    #[test]
+    fn custom_pattern_uses_its_first_group() {
+        let config = config(Some(r"depth=(\d+)"));
+        assert_eq!(config.parse("id=7 depth=42"), Some(42.0));
+    }
+
+    #[test]
+    fn custom_pattern_without_a_group_uses_the_match() {
+        let config = config(Some(r"\d+(?:\.\d+)?"));
+        assert_eq!(config.parse("took 30.5ms"), Some(30.5));
+    }
+}
@@ -1,21 +1,113 @@
+use std::fs;
+use std::path::{Path, PathBuf};
 use sysinfo::System;
 
-use super::MetricValue;
+use super::{Metric, MetricSource, MetricValue};
+
+/// A core's frequency limits in kHz, from cpufreq
+#[derive(Debug, Clone, Copy)]
+struct FrequencyRange {
+    min_khz: f64,
+    max_khz: f64,
+}
 
 pub struct CpuMonitor {
     system: System,
+    /// Directory holding the cpuN/cpufreq directories
+    root: PathBuf,
+    /// Per-core limits from cpufreq (None when cpufreq is unavailable for that core)
+    frequency_ranges: Vec<Option<FrequencyRange>>,
+    /// Per-core frequency at last measurement, normalized between min and max
+    frequencies: Vec<MetricValue>,
+    /// Highest frequency seen via sysinfo, used when cpufreq limits are unavailable
+    max_observed_mhz: u64,
 }
 
 impl CpuMonitor {
+    const CPUFREQ_ROOT: &'static str = "/sys/devices/system/cpu";
+
     pub fn new() -> Self {
+        Self::with_root(Self::CPUFREQ_ROOT)
+    }
+
+    /// Create a monitor that reads cpufreq below `root` instead of /sys/devices/system/cpu
+    pub fn with_root(root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref().to_path_buf();
         let mut system = System::new();
         // Initial refresh to get baseline
         system.refresh_cpu_usage();
-        Self { system }
+
+        let frequency_ranges = (0..system.cpus().len())
+            .map(|core| Self::read_frequency_range(&root, core))
+            .collect();
+
+        Self {
+            system,
+            root,
+            frequency_ranges,
+            frequencies: Vec::new(),
+            max_observed_mhz: 0,
+        }
     }
 
     pub fn refresh(&mut self) {
         self.system.refresh_cpu_usage();
+        self.refresh_frequencies();
+    }
+
+    /// Read current per-core frequencies, from cpufreq where possible and sysinfo otherwise
+    fn refresh_frequencies(&mut self) {
+        let current: Vec<_> = self
+            .frequency_ranges
+            .iter()
+            .enumerate()
+            .map(|(core, range)| {
+                range.and_then(|range| {
+                    Self::read_cpufreq_khz(&self.root, core, "scaling_cur_freq")
+                        .map(|khz| (khz, range))
+                })
+            })
+            .collect();
+
+        // Any core without a cpufreq reading falls back to sysinfo
+        if current.iter().any(Option::is_none) {
+            self.system.refresh_cpu_frequency();
+            let highest = self
+                .system
+                .cpus()
+                .iter()
+                .map(|c| c.frequency())
+                .max()
+                .unwrap_or(0);
+            self.max_observed_mhz = self.max_observed_mhz.max(highest);
+        }
+
+        self.frequencies = current
+            .into_iter()
+            .enumerate()
+            .map(|(core, current)| {
+                match current {
+                    Some((khz, range)) => {
+                        let span = (range.max_khz - range.min_khz).max(1.0);
+                        MetricValue::new((khz - range.min_khz) / span)
+                    }
+                    None => {
+                        // No cpufreq reading: scale against the fastest frequency seen so far
+                        let mhz = self
+                            .system
+                            .cpus()
+                            .get(core)
+                            .map(|c| c.frequency())
+                            .unwrap_or(0);
+                        if self.max_observed_mhz == 0 {
+                            MetricValue::new(0.0)
+                        } else {
+                            MetricValue::new(mhz as f64 / self.max_observed_mhz as f64)
+                        }
+                    }
+                }
+            })
+            .collect();
     }
 
     /// Returns CPU usage for each core as a value between 0.0 and 1.0
@@ -27,6 +119,12 @@ impl CpuMonitor {
             .collect()
     }
 
+    /// Returns current frequency for each core, normalized between its minimum (0.0)
+    /// and maximum (1.0) frequency
+    pub fn per_core_frequency(&self) -> Vec<MetricValue> {
+        self.frequencies.clone()
+    }
+
     /// Returns average CPU usage across all cores
     pub fn average_usage(&self) -> MetricValue {
         let cpus = self.system.cpus();
@@ -42,6 +140,19 @@ impl CpuMonitor {
     pub fn core_count(&self) -> usize {
         self.system.cpus().len()
     }
+
+    /// Read a core's frequency limits from `root`/cpuN/cpufreq
+    fn read_frequency_range(root: &Path, core: usize) -> Option<FrequencyRange> {
+        let min_khz = Self::read_cpufreq_khz(root, core, "cpuinfo_min_freq")?;
+        let max_khz = Self::read_cpufreq_khz(root, core, "cpuinfo_max_freq")?;
+        (max_khz > min_khz).then_some(FrequencyRange { min_khz, max_khz })
+    }
+
+    fn read_cpufreq_khz(root: &Path, core: usize, file: &str) -> Option<f64> {
+        fs::read_to_string(root.join(format!("cpu{}/cpufreq/{}", core, file)))
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+    }
 }
 
 impl Default for CpuMonitor {
@@ -49,3 +160,91 @@ impl Default for CpuMonitor {
         Self::new()
     }
 }
+
+impl MetricSource for CpuMonitor {
+    fn name(&self) -> &str {
+        "cpu"
+    }
+
+    fn refresh(&mut self) {
+        CpuMonitor::refresh(self);
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        let mut values = vec![Metric::new("cpu.average", self.average_usage())];
+        for (core, usage) in self.per_core_usage().into_iter().enumerate() {
+            values.push(Metric::instance("cpu.core", core.to_string(), usage));
+        }
+        for (core, frequency) in self.per_core_frequency().into_iter().enumerate() {
+            values.push(Metric::instance(
+                "cpu.frequency",
+                core.to_string(),
+                frequency,
+            ));
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::write_files;
+
+    /// Give every core the same cpufreq limits, and `current` as its frequency if set
+    fn cpufreq(root: &Path, current: Option<&str>) {
+        for core in 0..CpuMonitor::with_root(root).core_count() {
+            let dir = format!("cpu{}/cpufreq", core);
+            write_files(
+                root,
+                &[
+                    (&format!("{}/cpuinfo_min_freq", dir), "800000\n"),
+                    (&format!("{}/cpuinfo_max_freq", dir), "3000000\n"),
+                ],
+            );
+            if let Some(current) = current {
+                write_files(root, &[(&format!("{}/scaling_cur_freq", dir), current)]);
+            }
+        }
+    }
+
+    #[test]
+    fn frequency_is_normalized_between_min_and_max() {
+        let root = tempfile::tempdir().unwrap();
+        cpufreq(root.path(), Some("1900000\n"));
+
+        let mut monitor = CpuMonitor::with_root(root.path());
+        monitor.refresh();
+        let frequencies = monitor.per_core_frequency();
+        assert_eq!(frequencies.len(), monitor.core_count());
+        assert!(frequencies.iter().all(|f| (f.get() - 0.5).abs() < 1e-9));
+        assert_eq!(monitor.max_observed_mhz, 0);
+    }
+
+    #[test]
+    fn unreadable_current_frequency_falls_back_to_sysinfo() {
+        let root = tempfile::tempdir().unwrap();
+        cpufreq(root.path(), None);
+
+        let mut monitor = CpuMonitor::with_root(root.path());
+        assert!(monitor.frequency_ranges.iter().all(Option::is_some));
+        monitor.refresh();
+
+        let mhz: Vec<u64> = monitor
+            .system
+            .cpus()
+            .iter()
+            .map(|c| c.frequency())
+            .collect();
+        let highest = mhz.iter().copied().max().unwrap_or(0);
+        assert_eq!(monitor.max_observed_mhz, highest);
+        for (frequency, mhz) in monitor.per_core_frequency().iter().zip(mhz) {
+            let expected = if highest == 0 {
+                0.0
+            } else {
+                mhz as f64 / highest as f64
+            };
+            assert!((frequency.get() - expected).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use super::{Metric, MetricSource, MetricValue, PowerMonitor, SystemMetrics};
+
+/// How the demo source moves every metric between idle and full load
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DemoPattern {
+    /// Rise steadily from idle to full, then start over
+    #[default]
+    Ramp,
+    /// Swell smoothly up to full and back down
+    Sine,
+    /// Wander randomly, independently for every voice
+    RandomWalk,
+    /// Ramp with each core (or device) starting a little after the previous one
+    Stagger,
+}
+
+impl DemoPattern {
+    pub fn from_name(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ramp" => Some(Self::Ramp),
+            "sine" => Some(Self::Sine),
+            "random" | "randomwalk" | "random-walk" => Some(Self::RandomWalk),
+            "stagger" => Some(Self::Stagger),
+            _ => None,
+        }
+    }
+}
+
+/// Synthetic load for auditioning packs: sweeps every metric from idle to full.
+///
+/// Publishes the metrics a pack's voices follow, with the same instances as the live
+/// sources publish, so registered after them it takes their place. At the end of each
+/// sweep external power is connected or disconnected, in turn.
+pub struct DemoSource {
+    pattern: DemoPattern,
+    /// Seconds per sweep
+    period: f64,
+    start: Instant,
+    /// Seconds since the start at the last refresh
+    last_refresh: f64,
+    /// Metric IDs with the labels of their instances
+    layout: Vec<(String, Vec<Option<String>>)>,
+    /// Current random walk level of every instance, in layout order
+    walk: Vec<f64>,
+    rng: u64,
+    /// Sweeps completed at the last refresh
+    sweeps: u64,
+    values: Vec<Metric>,
+}
+
+impl DemoSource {
+    pub const DEFAULT_PERIOD: f64 = 8.0;
+
+    /// Create a demo that sweeps `metrics`, with the instances (cores, devices...) each
+    /// has in `template`. Metrics missing from it, such as ones that are only published
+    /// once something is pushed or scraped, get a single instance.
+    pub fn new(pattern: DemoPattern, metrics: &[String], template: &SystemMetrics) -> Self {
+        let mut layout: Vec<(String, Vec<Option<String>>)> = metrics
+            .iter()
+            .filter(|id| !PowerMonitor::EVENTS.contains(&id.as_str()))
+            .map(|id| {
+                let labels = template
+                    .iter()
+                    .find(|(template_id, _)| template_id == id)
+                    .map(|(_, instances)| {
+                        instances.iter().map(|(label, _)| label.clone()).collect()
+                    })
+                    .unwrap_or_else(|| vec![None]);
+                (id.clone(), labels)
+            })
+            .collect();
+        layout.sort_by(|a, b| a.0.cmp(&b.0));
+        layout.dedup_by(|a, b| a.0 == b.0);
+
+        let voices = layout.iter().map(|(_, labels)| labels.len()).sum();
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+
+        Self {
+            pattern,
+            period: Self::DEFAULT_PERIOD,
+            start: Instant::now(),
+            last_refresh: 0.0,
+            layout,
+            walk: vec![0.0; voices],
+            rng: seed | 1,
+            sweeps: 0,
+            values: Vec::new(),
+        }
+    }
+
+    /// Next pseudo-random number between -1.0 and 1.0 (xorshift)
+    fn random(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+    }
+
+    /// Move the demo to `elapsed` seconds after its start, for driving it faster
+    /// (or slower) than real time. `refresh` does the same with the time since creation.
+    pub fn advance_to(&mut self, elapsed: f64) {
+        let step = (elapsed - self.last_refresh).max(0.0) / self.period;
+        self.last_refresh = elapsed;
+        let elapsed = elapsed / self.period;
+
+        let phase = elapsed.fract();
+        let sweeps = elapsed.floor() as u64;
+        let sweep_ended = sweeps != self.sweeps;
+        self.sweeps = sweeps;
+
+        let mut values = Vec::new();
+        let mut voice = 0;
+        for entry in 0..self.layout.len() {
+            let count = self.layout[entry].1.len();
+            for index in 0..count {
+                let level = if self.pattern == DemoPattern::RandomWalk {
+                    // Fast enough to cross the whole range in about half a sweep
+                    let mut next = self.walk[voice] + self.random() * step * 4.0;
+                    // Bounce off the ends rather than sticking to them
+                    if next < 0.0 {
+                        next = -next;
+                    } else if next > 1.0 {
+                        next = 2.0 - next;
+                    }
+                    self.walk[voice] = next.clamp(0.0, 1.0);
+                    self.walk[voice]
+                } else {
+                    self.level(phase, index, count)
+                };
+                voice += 1;
+
+                let (id, labels) = &self.layout[entry];
+                let value = MetricValue::new(level);
+                values.push(match labels[index] {
+                    Some(ref label) => Metric::instance(id, label.clone(), value),
+                    None => Metric::new(id, value),
+                });
+            }
+        }
+
+        // Like a real charger, power is connected after one sweep and disconnected after the next
+        let fired = sweep_ended.then(|| PowerMonitor::EVENTS[(sweeps + 1) as usize % 2]);
+        for event in PowerMonitor::EVENTS {
+            let level = if fired == Some(event) { 1.0 } else { 0.0 };
+            values.push(Metric::new(event, MetricValue::new(level)));
+        }
+
+        self.values = values;
+    }
+
+    /// Level of one instance at `phase` (0.0-1.0 through the sweep)
+    fn level(&self, phase: f64, index: usize, count: usize) -> f64 {
+        match self.pattern {
+            DemoPattern::Ramp => phase,
+            DemoPattern::Sine => 0.5 - 0.5 * (phase * std::f64::consts::TAU).cos(),
+            DemoPattern::Stagger => (phase - index as f64 / count as f64).rem_euclid(1.0),
+            DemoPattern::RandomWalk => 0.0,
+        }
+    }
+}
+
+impl MetricSource for DemoSource {
+    fn name(&self) -> &str {
+        "demo"
+    }
+
+    fn refresh(&mut self) {
+        self.advance_to(self.start.elapsed().as_secs_f64());
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        self.values.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(metrics: &[&str]) -> Vec<String> {
+        metrics.iter().map(|id| id.to_string()).collect()
+    }
+
+    /// Events that fired at the last refresh
+    fn fired(demo: &DemoSource) -> Vec<String> {
+        demo.values()
+            .into_iter()
+            .filter(|m| PowerMonitor::EVENTS.contains(&m.id.as_str()) && m.value.get() == 1.0)
+            .map(|m| m.id)
+            .collect()
+    }
+
+    #[test]
+    fn bound_metrics_are_swept_with_the_instances_they_have() {
+        let mut template = SystemMetrics::default();
+        template.insert(vec![
+            Metric::instance("cpu.core", "0".to_string(), MetricValue::new(0.2)),
+            Metric::instance("cpu.core", "1".to_string(), MetricValue::new(0.4)),
+            Metric::new("memory.used", MetricValue::new(0.5)),
+        ]);
+        let metrics = ids(&["cpu.core", "push.queue", "cpu.core", "power.connected"]);
+        let mut demo = DemoSource::new(DemoPattern::Ramp, &metrics, &template);
+        demo.advance_to(2.0);
+
+        let mut swept = SystemMetrics::default();
+        swept.insert(demo.values());
+        assert_eq!(swept.instances("cpu.core").len(), 2);
+        // Not published yet, but bound, so it's demoed all the same
+        assert_eq!(swept.get("push.queue").get(), 0.25);
+        // Published but not bound
+        assert!(swept.instances("memory.used").is_empty());
+    }
+
+    #[test]
+    fn power_is_connected_and_disconnected_in_turn() {
+        let mut demo = DemoSource::new(DemoPattern::Ramp, &[], &SystemMetrics::default());
+        let period = DemoSource::DEFAULT_PERIOD;
+
+        demo.advance_to(period * 0.5);
+        assert!(fired(&demo).is_empty());
+        demo.advance_to(period * 1.1);
+        assert_eq!(fired(&demo), ["power.connected"]);
+        demo.advance_to(period * 1.5);
+        assert!(fired(&demo).is_empty());
+        demo.advance_to(period * 2.1);
+        assert_eq!(fired(&demo), ["power.disconnected"]);
+        demo.advance_to(period * 3.1);
+        assert_eq!(fired(&demo), ["power.connected"]);
+    }
+
+    #[test]
+    fn stagger_starts_each_instance_later() {
+        let mut template = SystemMetrics::default();
+        template.insert(
+            (0..4)
+                .map(|core| Metric::instance("cpu.core", core.to_string(), MetricValue::default()))
+                .collect(),
+        );
+        let mut demo = DemoSource::new(DemoPattern::Stagger, &ids(&["cpu.core"]), &template);
+        demo.advance_to(DemoSource::DEFAULT_PERIOD * 0.5);
+
+        let mut swept = SystemMetrics::default();
+        swept.insert(demo.values());
+        let levels: Vec<f64> = swept
+            .instances("cpu.core")
+            .iter()
+            .map(|v| v.get())
+            .collect();
+        assert_eq!(levels, [0.5, 0.25, 0.0, 0.75]);
+    }
+
+    #[test]
+    fn random_walk_stays_in_range() {
+        let metrics = ids(&["memory.used", "network.rx"]);
+        let mut demo =
+            DemoSource::new(DemoPattern::RandomWalk, &metrics, &SystemMetrics::default());
+        for tick in 1..1000 {
+            demo.advance_to(tick as f64 * 0.1);
+            assert!(demo
+                .values()
+                .iter()
+                .all(|m| (0.0..=1.0).contains(&m.value.get())));
+        }
+    }
+}
@@ -1,17 +1,104 @@
 use std::fs;
 use std::time::Instant;
 
-use super::MetricValue;
+use super::{DecayingMax, Metric, MetricSource, MetricValue};
 
-/// Monitors disk I/O activity by reading /proc/diskstats
-pub struct DiskMonitor {
+/// Selects which block devices are monitored, by name or glob pattern (`*` and `?`)
+#[derive(Debug, Clone, Default)]
+pub struct DeviceFilter {
+    /// Only monitor devices matching one of these patterns.
+    /// When empty, all whole physical devices are monitored.
+    pub include: Vec<String>,
+    /// Never monitor devices matching one of these patterns
+    pub exclude: Vec<String>,
+}
+
+impl DeviceFilter {
+    /// Check if a device from /proc/diskstats should be monitored.
+    /// Explicit include patterns may select partitions or virtual devices too.
+    pub fn selects(&self, device_name: &str) -> bool {
+        if self.exclude.iter().any(|p| glob_match(p, device_name)) {
+            return false;
+        }
+
+        if self.include.is_empty() {
+            DiskMonitor::is_physical_device(device_name)
+        } else {
+            self.include.iter().any(|p| glob_match(p, device_name))
+        }
+    }
+}
+
+/// Match a name against a glob pattern supporting `*` (any run) and `?` (any one character)
+pub(super) fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Position of the last '*' in the pattern and the name position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            // Let the last '*' swallow one more character and retry
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Activity tracking for a single block device
+struct DeviceActivity {
+    name: String,
     last_read_sectors: u64,
     last_write_sectors: u64,
+    /// Sectors per second at last measurement
+    activity_level: f64,
+    /// Maximum observed activity for normalization
+    max_activity: DecayingMax,
+}
+
+impl DeviceActivity {
+    fn new(name: String, read_sectors: u64, write_sectors: u64) -> Self {
+        Self {
+            name,
+            last_read_sectors: read_sectors,
+            last_write_sectors: write_sectors,
+            activity_level: 0.0,
+            max_activity: DecayingMax::new(DiskMonitor::MIN_MAX_ACTIVITY),
+        }
+    }
+}
+
+/// Monitors disk I/O activity by reading /proc/diskstats
+pub struct DiskMonitor {
+    filter: DeviceFilter,
+    /// Selected devices, sorted by name, which is the order their voices are laid out in
+    devices: Vec<DeviceActivity>,
     last_time: Instant,
     /// Sectors per second at last measurement
     activity_level: f64,
     /// Maximum observed activity for normalization
-    max_activity: f64,
+    max_activity: DecayingMax,
+    /// Sectors read per second at last measurement
+    read_level: f64,
+    /// Maximum observed read activity for normalization
+    max_read: DecayingMax,
+    /// Sectors written per second at last measurement
+    write_level: f64,
+    /// Maximum observed write activity for normalization
+    max_write: DecayingMax,
 }
 
 impl DiskMonitor {
@@ -19,46 +106,119 @@ impl DiskMonitor {
     const MIN_MAX_ACTIVITY: f64 = 1000.0;
 
     pub fn new() -> Self {
-        let (read_sectors, write_sectors) = Self::read_disk_stats();
+        Self::with_filter(DeviceFilter::default())
+    }
+
+    /// Create a monitor that only tracks devices selected by `filter`
+    pub fn with_filter(filter: DeviceFilter) -> Self {
+        let mut stats = Self::read_disk_stats(&filter);
+        stats.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let devices = stats
+            .into_iter()
+            .map(|(name, read, write)| DeviceActivity::new(name, read, write))
+            .collect();
+
         Self {
-            last_read_sectors: read_sectors,
-            last_write_sectors: write_sectors,
+            filter,
+            devices,
             last_time: Instant::now(),
             activity_level: 0.0,
-            max_activity: Self::MIN_MAX_ACTIVITY,
+            max_activity: DecayingMax::new(Self::MIN_MAX_ACTIVITY),
+            read_level: 0.0,
+            max_read: DecayingMax::new(Self::MIN_MAX_ACTIVITY),
+            write_level: 0.0,
+            max_write: DecayingMax::new(Self::MIN_MAX_ACTIVITY),
         }
     }
 
     pub fn refresh(&mut self) {
-        let (read_sectors, write_sectors) = Self::read_disk_stats();
-        let now = Instant::now();
+        let stats = Self::read_disk_stats(&self.filter);
+        self.update(stats, Instant::now());
+    }
+
+    /// Take in the sectors read and written by each selected device at `now`
+    fn update(&mut self, stats: Vec<(String, u64, u64)>, now: Instant) {
         let elapsed = now.duration_since(self.last_time).as_secs_f64();
 
+        // Devices that disappeared (e.g. unplugged) are dropped
+        self.devices
+            .retain(|d| stats.iter().any(|(name, _, _)| *name == d.name));
+
+        let mut read_delta = 0u64;
+        let mut write_delta = 0u64;
+
+        for (name, read_sectors, write_sectors) in stats {
+            let device = match self.devices.iter_mut().find(|d| d.name == name) {
+                Some(device) => device,
+                None => {
+                    // Newly attached device: start from its current counters
+                    let index = self.devices.partition_point(|d| d.name < name);
+                    self.devices.insert(
+                        index,
+                        DeviceActivity::new(name, read_sectors, write_sectors),
+                    );
+                    continue;
+                }
+            };
+
+            let device_read = read_sectors.saturating_sub(device.last_read_sectors);
+            let device_write = write_sectors.saturating_sub(device.last_write_sectors);
+            device.last_read_sectors = read_sectors;
+            device.last_write_sectors = write_sectors;
+
+            if elapsed > 0.0 {
+                device.activity_level = (device_read + device_write) as f64 / elapsed;
+                device.max_activity.update(device.activity_level);
+            }
+
+            read_delta += device_read;
+            write_delta += device_write;
+        }
+
         if elapsed > 0.0 {
-            let read_delta = read_sectors.saturating_sub(self.last_read_sectors);
-            let write_delta = write_sectors.saturating_sub(self.last_write_sectors);
             let total_delta = read_delta + write_delta;
 
             // Sectors per second
             self.activity_level = total_delta as f64 / elapsed;
+            self.read_level = read_delta as f64 / elapsed;
+            self.write_level = write_delta as f64 / elapsed;
 
-            // Update max for normalization (with decay to adapt to changing workloads)
-            if self.activity_level > self.max_activity {
-                self.max_activity = self.activity_level;
-            } else {
-                // Slow decay of max activity
-                self.max_activity = (self.max_activity * 0.999).max(Self::MIN_MAX_ACTIVITY);
-            }
+            self.max_activity.update(self.activity_level);
+            self.max_read.update(self.read_level);
+            self.max_write.update(self.write_level);
         }
 
-        self.last_read_sectors = read_sectors;
-        self.last_write_sectors = write_sectors;
         self.last_time = now;
     }
 
     /// Returns disk activity as a normalized value between 0.0 and 1.0
     pub fn activity(&self) -> MetricValue {
-        MetricValue::new(self.activity_level / self.max_activity)
+        self.max_activity.normalize(self.activity_level)
+    }
+
+    /// Returns disk read activity as a normalized value between 0.0 and 1.0
+    pub fn read_activity(&self) -> MetricValue {
+        self.max_read.normalize(self.read_level)
+    }
+
+    /// Returns disk write activity as a normalized value between 0.0 and 1.0
+    pub fn write_activity(&self) -> MetricValue {
+        self.max_write.normalize(self.write_level)
+    }
+
+    /// Returns activity for each monitored device, each normalized against its own max
+    pub fn per_device_activity(&self) -> Vec<MetricValue> {
+        self.devices
+            .iter()
+            .map(|d| d.max_activity.normalize(d.activity_level))
+            .collect()
+    }
+
+    /// Returns the names of the monitored devices, sorted, in the same order as
+    /// `per_device_activity`. Changes as devices are attached and removed.
+    pub fn device_names(&self) -> Vec<&str> {
+        self.devices.iter().map(|d| d.name.as_str()).collect()
     }
 
     /// Check if a device name represents a physical (whole) device rather than a partition.
@@ -74,25 +234,28 @@ impl DiskMonitor {
 
         // NVMe devices: nvme0n1 is base, nvme0n1p1 is partition
         // The 'p' before the partition number distinguishes partitions
-        if device_name.starts_with("nvme") {
+        if let Some(controller) = device_name.strip_prefix("nvme") {
             // Find the 'n' that separates controller from namespace
             // Base device: nvme0n1, nvme0n1 (no 'p' after the namespace number)
             // Partition: nvme0n1p1, nvme0n1p2 (has 'p' followed by partition number)
             // Check if there's a 'p' after 'n<digit>'
-            if let Some(n_pos) = device_name.find('n') {
-                let after_n = &device_name[n_pos + 1..];
+            if let Some(n_pos) = controller.find('n') {
+                let after_n = &controller[n_pos + 1..];
                 // Skip digits after 'n' (namespace number), then check for 'p'
-                let after_namespace: String = after_n.chars().skip_while(|c| c.is_ascii_digit()).collect();
+                let after_namespace: String =
+                    after_n.chars().skip_while(|c| c.is_ascii_digit()).collect();
                 return !after_namespace.starts_with('p');
             }
             return true;
         }
 
         // MMC/SD cards: mmcblk0 is base, mmcblk0p1 is partition
-        if device_name.starts_with("mmcblk") {
+        if let Some(after_prefix) = device_name.strip_prefix("mmcblk") {
             // Similar to NVMe - partitions have 'p' before partition number
-            let after_prefix = &device_name[6..]; // Skip "mmcblk"
-            let after_device_num: String = after_prefix.chars().skip_while(|c| c.is_ascii_digit()).collect();
+            let after_device_num: String = after_prefix
+                .chars()
+                .skip_while(|c| c.is_ascii_digit())
+                .collect();
             return !after_device_num.starts_with('p');
         }
 
@@ -105,15 +268,14 @@ impl DiskMonitor {
             .unwrap_or(false)
     }
 
-    /// Read total sectors read/written from /proc/diskstats
-    fn read_disk_stats() -> (u64, u64) {
+    /// Read sectors read/written for each selected device from /proc/diskstats
+    fn read_disk_stats(filter: &DeviceFilter) -> Vec<(String, u64, u64)> {
         let content = match fs::read_to_string("/proc/diskstats") {
             Ok(c) => c,
-            Err(_) => return (0, 0),
+            Err(_) => return Vec::new(),
         };
 
-        let mut total_read = 0u64;
-        let mut total_write = 0u64;
+        let mut stats = Vec::new();
 
         for line in content.lines() {
             let parts: Vec<&str> = line.split_whitespace().collect();
@@ -123,19 +285,19 @@ impl DiskMonitor {
 
             let device_name = parts[2];
 
-            // Only count whole/physical devices, skip partitions and virtual devices
-            if !Self::is_physical_device(device_name) {
+            // By default only whole/physical devices are counted, skipping partitions
+            // and virtual devices; the filter may narrow or widen that selection
+            if !filter.selects(device_name) {
                 continue;
             }
 
             // Field 6 is sectors read, field 10 is sectors written (0-indexed from field 3)
             if let (Ok(read), Ok(write)) = (parts[5].parse::<u64>(), parts[9].parse::<u64>()) {
-                total_read += read;
-                total_write += write;
+                stats.push((device_name.to_string(), read, write));
             }
         }
 
-        (total_read, total_write)
+        stats
     }
 }
 
@@ -144,3 +306,119 @@ impl Default for DiskMonitor {
         Self::new()
     }
 }
+
+impl MetricSource for DiskMonitor {
+    fn name(&self) -> &str {
+        "disk"
+    }
+
+    fn refresh(&mut self) {
+        DiskMonitor::refresh(self);
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        let mut values = vec![
+            Metric::new("disk.activity", self.activity()),
+            Metric::new("disk.read", self.read_activity()),
+            Metric::new("disk.write", self.write_activity()),
+        ];
+        for (name, activity) in self
+            .device_names()
+            .into_iter()
+            .zip(self.per_device_activity())
+        {
+            values.push(Metric::instance("disk.device", name, activity));
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> DeviceFilter {
+        DeviceFilter {
+            include: include.iter().map(|p| p.to_string()).collect(),
+            exclude: exclude.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    fn stats(devices: &[&str]) -> Vec<(String, u64, u64)> {
+        devices
+            .iter()
+            .map(|name| (name.to_string(), 0, 0))
+            .collect()
+    }
+
+    #[test]
+    fn glob_stars_and_question_marks() {
+        assert!(glob_match("*", "sda"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("sd*", "sd"));
+        assert!(glob_match("sd?", "sdb"));
+        assert!(!glob_match("sd?", "sd"));
+        assert!(!glob_match("sd?", "sdb1"));
+        assert!(glob_match("*a", "sda"));
+        assert!(glob_match("s*d*a", "sda"));
+        assert!(!glob_match("sda", "sdb"));
+        assert!(!glob_match("", "sda"));
+    }
+
+    #[test]
+    fn glob_stars_backtrack() {
+        assert!(glob_match("nvme*n1", "nvme0n1"));
+        assert!(glob_match("nvme*n1", "nvme10n1"));
+        assert!(!glob_match("nvme*n1", "nvme0n1p1"));
+        assert!(glob_match("nvme*n1*", "nvme0n1p1"));
+        assert!(glob_match("*n1p?", "nvme0n1n1p2"));
+        assert!(!glob_match("*n1p?", "nvme0n1p12"));
+    }
+
+    #[test]
+    fn default_filter_selects_whole_physical_devices() {
+        let filter = DeviceFilter::default();
+        assert!(filter.selects("sda"));
+        assert!(filter.selects("nvme0n1"));
+        assert!(filter.selects("mmcblk0"));
+        assert!(!filter.selects("mmcblk0p1"));
+        assert!(!filter.selects("sda1"));
+        assert!(!filter.selects("nvme0n1p2"));
+        assert!(!filter.selects("loop0"));
+        assert!(!filter.selects("dm-0"));
+    }
+
+    #[test]
+    fn include_patterns_widen_and_exclude_patterns_win() {
+        let filter = filter(&["nvme*", "sda1"], &["nvme1*"]);
+        assert!(filter.selects("nvme0n1"));
+        assert!(filter.selects("nvme0n1p1"));
+        assert!(filter.selects("sda1"));
+        assert!(!filter.selects("sda"));
+        assert!(!filter.selects("nvme1n1"));
+
+        let filter = self::filter(&[], &["sd?"]);
+        assert!(!filter.selects("sdb"));
+        assert!(filter.selects("nvme0n1"));
+        assert!(!self::filter(&["sdb"], &["sdb"]).selects("sdb"));
+    }
+
+    #[test]
+    fn attached_devices_are_kept_sorted_and_removed_ones_dropped() {
+        let mut monitor = DiskMonitor::with_filter(filter(&["none"], &[]));
+        let start = Instant::now();
+        monitor.update(stats(&["sdb", "nvme0n1"]), start);
+        assert_eq!(monitor.device_names(), ["nvme0n1", "sdb"]);
+
+        monitor.update(
+            stats(&["sdb", "nvme0n1", "sda"]),
+            start + Duration::from_secs(1),
+        );
+        assert_eq!(monitor.device_names(), ["nvme0n1", "sda", "sdb"]);
+
+        monitor.update(stats(&["sda", "sdb"]), start + Duration::from_secs(2));
+        assert_eq!(monitor.device_names(), ["sda", "sdb"]);
+        assert_eq!(monitor.per_device_activity().len(), 2);
+    }
+}
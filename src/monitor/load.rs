@@ -0,0 +1,147 @@
+use std::fs;
+
+use super::{Metric, MetricSource, MetricValue};
+
+/// Which figure the Load channel follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoadSource {
+    /// 1-minute load average
+    #[default]
+    Average,
+    /// Processes currently runnable
+    Running,
+}
+
+impl LoadSource {
+    /// ID of the metric this figure is published as
+    pub fn metric_id(&self) -> &'static str {
+        match self {
+            Self::Average => "load.average",
+            Self::Running => "load.running",
+        }
+    }
+
+    pub fn from_name(value: &str) -> Self {
+        if value.trim().eq_ignore_ascii_case("running") {
+            Self::Running
+        } else {
+            Self::Average
+        }
+    }
+}
+
+/// Monitors the load average (/proc/loadavg) and run queue (/proc/stat).
+/// All figures are normalized against the number of CPU cores, so 1.0 means
+/// one task per core.
+pub struct LoadMonitor {
+    core_count: usize,
+    /// 1-minute load average
+    load_average: f64,
+    /// Runnable processes, excluding ourselves
+    procs_running: u64,
+    /// Processes blocked waiting for IO
+    procs_blocked: u64,
+}
+
+impl LoadMonitor {
+    pub fn new(core_count: usize) -> Self {
+        Self {
+            core_count: core_count.max(1),
+            load_average: 0.0,
+            procs_running: 0,
+            procs_blocked: 0,
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        self.load_average = Self::read_load_average();
+
+        let (running, blocked) = Self::read_run_queue();
+        // procs_running always counts the process reading it
+        self.procs_running = running.saturating_sub(1);
+        self.procs_blocked = blocked;
+    }
+
+    /// Returns the 1-minute load average per core
+    pub fn load_average(&self) -> MetricValue {
+        MetricValue::new(self.load_average / self.core_count as f64)
+    }
+
+    /// Returns runnable processes per core
+    pub fn running(&self) -> MetricValue {
+        MetricValue::new(self.procs_running as f64 / self.core_count as f64)
+    }
+
+    /// Returns processes blocked on IO per core
+    pub fn blocked(&self) -> MetricValue {
+        MetricValue::new(self.procs_blocked as f64 / self.core_count as f64)
+    }
+
+    /// Read the 1-minute load average from /proc/loadavg
+    fn read_load_average() -> f64 {
+        fs::read_to_string("/proc/loadavg")
+            .ok()
+            .and_then(|c| c.split_whitespace().next().and_then(|v| v.parse().ok()))
+            .unwrap_or(0.0)
+    }
+
+    /// Read procs_running and procs_blocked from /proc/stat
+    fn read_run_queue() -> (u64, u64) {
+        let content = match fs::read_to_string("/proc/stat") {
+            Ok(c) => c,
+            Err(_) => return (0, 0),
+        };
+
+        let mut running = 0u64;
+        let mut blocked = 0u64;
+
+        for line in content.lines() {
+            match line.split_once(' ') {
+                Some(("procs_running", v)) => running = v.trim().parse().unwrap_or(0),
+                Some(("procs_blocked", v)) => blocked = v.trim().parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+
+        (running, blocked)
+    }
+}
+
+impl MetricSource for LoadMonitor {
+    fn name(&self) -> &str {
+        "load"
+    }
+
+    fn refresh(&mut self) {
+        LoadMonitor::refresh(self);
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        vec![
+            Metric::new("load.average", self.load_average()),
+            Metric::new("load.running", self.running()),
+            Metric::new("load.blocked", self.blocked()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocked_processes_scale_with_cores() {
+        let mut monitor = LoadMonitor::new(16);
+        monitor.procs_blocked = 4;
+        assert_eq!(monitor.blocked().get(), 0.25);
+        monitor.procs_blocked = 20;
+        assert_eq!(monitor.blocked().get(), 1.0);
+    }
+
+    #[test]
+    fn running_processes_scale_with_cores() {
+        let mut monitor = LoadMonitor::new(4);
+        monitor.procs_running = 2;
+        assert_eq!(monitor.running().get(), 0.5);
+    }
+}
@@ -1,20 +1,69 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use sysinfo::System;
 
-use super::MetricValue;
+use super::{DecayingMax, Metric, MetricSource, MetricValue};
 
 pub struct MemoryMonitor {
     system: System,
+    /// Where vmstat is read from
+    vmstat_path: PathBuf,
+    last_swap_in: u64,
+    last_swap_out: u64,
+    last_time: Instant,
+    /// Pages swapped in or out per second at last measurement
+    paging_level: f64,
+    /// Maximum observed paging rate for normalization
+    max_paging: DecayingMax,
 }
 
 impl MemoryMonitor {
+    /// Minimum paging threshold to avoid division by very small numbers
+    const MIN_MAX_PAGING: f64 = 100.0;
+
+    const PROC_ROOT: &'static str = "/proc";
+
     pub fn new() -> Self {
+        Self::with_root(Self::PROC_ROOT)
+    }
+
+    /// Create a monitor that reads vmstat below `root` instead of /proc
+    pub fn with_root(root: impl AsRef<Path>) -> Self {
         let mut system = System::new();
         system.refresh_memory();
-        Self { system }
+        let vmstat_path = root.as_ref().join("vmstat");
+        let (swap_in, swap_out) = Self::read_swap_stats(&vmstat_path);
+        Self {
+            system,
+            vmstat_path,
+            last_swap_in: swap_in,
+            last_swap_out: swap_out,
+            last_time: Instant::now(),
+            paging_level: 0.0,
+            max_paging: DecayingMax::new(Self::MIN_MAX_PAGING),
+        }
     }
 
     pub fn refresh(&mut self) {
         self.system.refresh_memory();
+
+        let (swap_in, swap_out) = Self::read_swap_stats(&self.vmstat_path);
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_time).as_secs_f64();
+
+        if elapsed > 0.0 {
+            let in_delta = swap_in.saturating_sub(self.last_swap_in);
+            let out_delta = swap_out.saturating_sub(self.last_swap_out);
+
+            // Pages per second
+            self.paging_level = (in_delta + out_delta) as f64 / elapsed;
+            self.max_paging.update(self.paging_level);
+        }
+
+        self.last_swap_in = swap_in;
+        self.last_swap_out = swap_out;
+        self.last_time = now;
     }
 
     /// Returns memory usage as a value between 0.0 and 1.0
@@ -29,6 +78,23 @@ impl MemoryMonitor {
         MetricValue::new(used as f64 / total as f64)
     }
 
+    /// Returns swap usage as a value between 0.0 and 1.0 (0.0 when there is no swap)
+    pub fn swap_usage(&self) -> MetricValue {
+        let total = self.system.total_swap();
+        let used = self.system.used_swap();
+
+        if total == 0 {
+            return MetricValue::new(0.0);
+        }
+
+        MetricValue::new(used as f64 / total as f64)
+    }
+
+    /// Returns swap-in/swap-out paging activity as a normalized value between 0.0 and 1.0
+    pub fn paging_activity(&self) -> MetricValue {
+        self.max_paging.normalize(self.paging_level)
+    }
+
     /// Returns total memory in bytes
     pub fn total_bytes(&self) -> u64 {
         self.system.total_memory()
@@ -38,6 +104,30 @@ impl MemoryMonitor {
     pub fn used_bytes(&self) -> u64 {
         self.system.used_memory()
     }
+
+    /// Read total pages swapped in/out from /proc/vmstat
+    fn read_swap_stats(path: &Path) -> (u64, u64) {
+        match fs::read_to_string(path) {
+            Ok(content) => Self::parse_swap_stats(&content),
+            Err(_) => (0, 0),
+        }
+    }
+
+    /// Total pages swapped in/out in /proc/vmstat contents
+    fn parse_swap_stats(content: &str) -> (u64, u64) {
+        let mut swap_in = 0u64;
+        let mut swap_out = 0u64;
+
+        for line in content.lines() {
+            match line.split_once(' ') {
+                Some(("pswpin", v)) => swap_in = v.trim().parse().unwrap_or(0),
+                Some(("pswpout", v)) => swap_out = v.trim().parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+
+        (swap_in, swap_out)
+    }
 }
 
 impl Default for MemoryMonitor {
@@ -45,3 +135,71 @@ impl Default for MemoryMonitor {
         Self::new()
     }
 }
+
+impl MetricSource for MemoryMonitor {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn refresh(&mut self) {
+        MemoryMonitor::refresh(self);
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        vec![
+            Metric::new("memory.usage", self.usage()),
+            Metric::new("swap.usage", self.swap_usage()),
+            Metric::new("swap.paging", self.paging_activity()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::write_files;
+    use std::thread;
+    use std::time::Duration;
+
+    fn vmstat(swap_in: u64, swap_out: u64) -> String {
+        format!(
+            "nr_free_pages 123456\npswpin {}\npswpout {}\npgfault 98765\n",
+            swap_in, swap_out
+        )
+    }
+
+    #[test]
+    fn reads_pages_swapped_in_and_out() {
+        assert_eq!(
+            MemoryMonitor::parse_swap_stats(&vmstat(1200, 3400)),
+            (1200, 3400)
+        );
+    }
+
+    #[test]
+    fn missing_swap_counters_read_as_zero() {
+        assert_eq!(
+            MemoryMonitor::parse_swap_stats("nr_free_pages 1\npswpout 7\n"),
+            (0, 7)
+        );
+        assert_eq!(MemoryMonitor::parse_swap_stats("pswpin x\n"), (0, 0));
+        assert_eq!(MemoryMonitor::parse_swap_stats(""), (0, 0));
+    }
+
+    #[test]
+    fn paging_is_normalized_between_refreshes() {
+        let root = tempfile::tempdir().unwrap();
+        write_files(root.path(), &[("vmstat", &vmstat(1000, 1000))]);
+        let mut monitor = MemoryMonitor::with_root(root.path());
+
+        // Far more than the floor of 100 pages a second
+        thread::sleep(Duration::from_millis(10));
+        write_files(root.path(), &[("vmstat", &vmstat(501_000, 501_000))]);
+        monitor.refresh();
+        assert_eq!(monitor.paging_activity().get(), 1.0);
+
+        thread::sleep(Duration::from_millis(10));
+        monitor.refresh();
+        assert_eq!(monitor.paging_activity().get(), 0.0);
+    }
+}
@@ -1,10 +1,42 @@
+mod cgroup;
+mod command;
 mod cpu;
+mod demo;
 mod disk;
+mod load;
 mod memory;
+mod network;
+mod poll;
+mod power;
+mod pressure;
+mod process;
+mod prometheus;
+mod push;
+mod recording;
+mod source;
+mod temperature;
 
+pub use cgroup::CgroupMonitor;
+pub use command::{CommandConfig, CommandSource};
 pub use cpu::CpuMonitor;
-pub use disk::DiskMonitor;
+pub use demo::{DemoPattern, DemoSource};
+pub use disk::{DeviceFilter, DiskMonitor};
+pub use load::{LoadMonitor, LoadSource};
 pub use memory::MemoryMonitor;
+pub use network::NetworkMonitor;
+pub use power::PowerMonitor;
+pub use pressure::{PressureMonitor, PressureStat};
+pub use process::{ProcessMonitor, ProcessSelector, ProcessSource};
+pub use prometheus::{PrometheusSource, ScrapeConfig, SeriesSelector};
+pub use push::{PushConfig, PushSource};
+pub use recording::{MetricRecorder, MetricReplay};
+pub use source::{Metric, MetricRegistry, MetricSource};
+pub use temperature::{TemperatureConfig, TemperatureMonitor};
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::config::Settings;
 
 /// Represents a normalized metric value between 0.0 and 1.0
 #[derive(Debug, Clone, Copy, Default)]
@@ -20,52 +52,203 @@ impl MetricValue {
     }
 }
 
-/// Combined system metrics snapshot
+/// Highest rate seen lately, for normalizing rates that have no natural maximum.
+/// Rises with the rate at once and otherwise decays slowly, to adapt to changing
+/// workloads, but never below a floor so that idle noise doesn't sound like full load.
+#[derive(Debug, Clone, Copy)]
+struct DecayingMax {
+    max: f64,
+    floor: f64,
+}
+
+impl DecayingMax {
+    /// Share of the max kept at each update that doesn't reach it
+    const DECAY: f64 = 0.999;
+
+    fn new(floor: f64) -> Self {
+        Self { max: floor, floor }
+    }
+
+    /// Take in the latest rate
+    fn update(&mut self, level: f64) {
+        if level > self.max {
+            self.max = level;
+        } else {
+            self.max = (self.max * Self::DECAY).max(self.floor);
+        }
+    }
+
+    /// A rate as a share of the max, between 0.0 and 1.0
+    fn normalize(&self, level: f64) -> MetricValue {
+        MetricValue::new(level / self.max)
+    }
+}
+
+/// Snapshot of every metric, keyed by metric ID
 #[derive(Debug, Clone, Default)]
 pub struct SystemMetrics {
-    /// Per-core CPU usage (0.0 to 1.0 each)
-    pub cpu_cores: Vec<MetricValue>,
-    /// Averaged CPU usage across all cores
-    pub cpu_average: MetricValue,
-    /// RAM usage percentage
-    pub memory: MetricValue,
-    /// Disk activity level
-    pub disk: MetricValue,
+    /// Readings per metric ID, in the order the source published them
+    values: HashMap<String, Vec<(Option<String>, MetricValue)>>,
+}
+
+impl SystemMetrics {
+    /// Add a source's readings, replacing earlier readings of the same metric IDs
+    pub fn insert(&mut self, metrics: Vec<Metric>) {
+        let mut replaced: Vec<String> = Vec::new();
+        for metric in metrics {
+            if !replaced.contains(&metric.id) {
+                self.values.remove(&metric.id);
+                replaced.push(metric.id.clone());
+            }
+            self.values
+                .entry(metric.id)
+                .or_default()
+                .push((metric.label, metric.value));
+        }
+    }
+
+    /// Returns the value of a metric (its first instance), or 0.0 if nothing publishes it
+    pub fn get(&self, id: &str) -> MetricValue {
+        self.values
+            .get(id)
+            .and_then(|instances| instances.first())
+            .map(|(_, value)| *value)
+            .unwrap_or_default()
+    }
+
+    /// Iterate over every metric ID with its labelled readings
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[(Option<String>, MetricValue)])> {
+        self.values
+            .iter()
+            .map(|(id, instances)| (id.as_str(), instances.as_slice()))
+    }
+
+    /// Returns every instance of a metric (e.g. one per CPU core)
+    pub fn instances(&self, id: &str) -> Vec<MetricValue> {
+        self.values
+            .get(id)
+            .map(|instances| instances.iter().map(|(_, value)| *value).collect())
+            .unwrap_or_default()
+    }
 }
 
 /// Central monitor that collects all system metrics
 pub struct SystemMonitor {
-    cpu: CpuMonitor,
-    memory: MemoryMonitor,
-    disk: DiskMonitor,
+    registry: MetricRegistry,
+    core_count: usize,
 }
 
 impl SystemMonitor {
     pub fn new() -> Self {
+        Self::with_settings(&Settings::default())
+    }
+
+    /// Create a monitor configured by the user settings
+    pub fn with_settings(settings: &Settings) -> Self {
+        let cpu = CpuMonitor::new();
+        let core_count = cpu.core_count();
+        let memory = MemoryMonitor::new();
+        let host_memory = memory.total_bytes();
+
+        let mut registry = MetricRegistry::new();
+        registry.register(Box::new(cpu));
+        registry.register(Box::new(LoadMonitor::new(core_count)));
+        registry.register(Box::new(memory));
+        registry.register(Box::new(DiskMonitor::with_filter(
+            settings.disk_filter.clone(),
+        )));
+        registry.register(Box::new(NetworkMonitor::new()));
+        registry.register(Box::new(TemperatureMonitor::new(
+            settings.temperature.clone(),
+        )));
+        registry.register(Box::new(PressureMonitor::new()));
+        registry.register(Box::new(PowerMonitor::new()));
+        registry.register(Box::new(ProcessMonitor::new(
+            settings.process.clone(),
+            core_count,
+        )));
+        for command in &settings.commands {
+            registry.register(Box::new(CommandSource::new(command.clone())));
+        }
+        for scrape in &settings.scrapes {
+            registry.register(Box::new(PrometheusSource::new(scrape.clone())));
+        }
+        if settings.push.is_enabled() {
+            registry.register(Box::new(PushSource::new(settings.push.clone())));
+        }
+
+        // Registered last so its CPU, memory and disk figures replace the host-wide ones
+        if let Some(ref path) = settings.cgroup {
+            registry.register(Box::new(CgroupMonitor::new(path, core_count, host_memory)));
+        }
+
         Self {
-            cpu: CpuMonitor::new(),
-            memory: MemoryMonitor::new(),
-            disk: DiskMonitor::new(),
+            registry,
+            core_count,
+        }
+    }
+
+    /// Replace the live readings of `metrics` with a synthetic sweep, or go back to
+    /// them with None
+    pub fn set_demo(&mut self, pattern: Option<DemoPattern>, metrics: &[String]) {
+        self.registry.unregister("demo");
+        if let Some(pattern) = pattern {
+            // The demo gives every metric as many instances as the live sources publish
+            let template = self.registry.refresh();
+            self.registry
+                .register(Box::new(DemoSource::new(pattern, metrics, &template)));
         }
     }
 
+    /// Add a metric source, replacing any source of the same name
+    pub fn register(&mut self, source: Box<dyn MetricSource>) {
+        self.registry.register(source);
+    }
+
     /// Refresh all metrics and return a snapshot
     pub fn refresh(&mut self) -> SystemMetrics {
-        self.cpu.refresh();
-        self.memory.refresh();
-        self.disk.refresh();
-
-        SystemMetrics {
-            cpu_cores: self.cpu.per_core_usage(),
-            cpu_average: self.cpu.average_usage(),
-            memory: self.memory.usage(),
-            disk: self.disk.activity(),
-        }
+        self.registry.refresh()
     }
 
     /// Returns the number of CPU cores
     pub fn core_count(&self) -> usize {
-        self.cpu.core_count()
+        self.core_count
+    }
+
+    /// Returns the cgroup being monitored, if any
+    pub fn cgroup_path(&self) -> Option<&Path> {
+        self.registry.get::<CgroupMonitor>().map(|c| c.path())
+    }
+
+    /// Returns the set of watched processes
+    pub fn process_selector(&self) -> ProcessSelector {
+        self.registry
+            .get::<ProcessMonitor>()
+            .map(|p| p.selector().clone())
+            .unwrap_or_default()
+    }
+
+    /// Replace the set of watched processes
+    pub fn set_process_selector(&mut self, selector: ProcessSelector) {
+        if let Some(process) = self.registry.get_mut::<ProcessMonitor>() {
+            process.set_selector(selector);
+        }
+    }
+
+    /// Returns the names of the busiest processes, for picking one to watch
+    pub fn top_process_names(&mut self, count: usize) -> Vec<String> {
+        self.registry
+            .get_mut::<ProcessMonitor>()
+            .map(|p| p.top_process_names(count))
+            .unwrap_or_default()
+    }
+
+    /// Returns the names of the monitored disk devices
+    pub fn disk_device_names(&self) -> Vec<&str> {
+        self.registry
+            .get::<DiskMonitor>()
+            .map(|d| d.device_names())
+            .unwrap_or_default()
     }
 }
 
@@ -74,3 +257,37 @@ impl Default for SystemMonitor {
         Self::new()
     }
 }
+
+/// Write `files` (relative path, contents) below `root`, for testing monitors
+/// against fake /proc and /sys trees
+#[cfg(test)]
+fn write_files(root: &Path, files: &[(&str, &str)]) {
+    for (path, contents) in files {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decaying_max_follows_peaks_and_settles_to_its_floor() {
+        let mut max = DecayingMax::new(100.0);
+        assert_eq!(max.normalize(50.0).get(), 0.5);
+
+        max.update(400.0);
+        assert_eq!(max.normalize(200.0).get(), 0.5);
+
+        max.update(0.0);
+        assert!((max.normalize(399.6).get() - 1.0).abs() < 1e-9);
+
+        for _ in 0..10_000 {
+            max.update(0.0);
+        }
+        assert_eq!(max.normalize(100.0).get(), 1.0);
+        assert_eq!(max.normalize(50.0).get(), 0.5);
+    }
+}
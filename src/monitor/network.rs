@@ -0,0 +1,154 @@
+use std::fs;
+use std::time::Instant;
+
+use super::{DecayingMax, Metric, MetricSource, MetricValue};
+
+/// Monitors network throughput by reading /proc/net/dev
+pub struct NetworkMonitor {
+    last_rx_bytes: u64,
+    last_tx_bytes: u64,
+    last_time: Instant,
+    /// Bytes per second at last measurement
+    activity_level: f64,
+    /// Maximum observed activity for normalization
+    max_activity: DecayingMax,
+}
+
+impl NetworkMonitor {
+    /// Minimum activity threshold to avoid division by very small numbers
+    const MIN_MAX_ACTIVITY: f64 = 10_000.0;
+
+    pub fn new() -> Self {
+        let (rx_bytes, tx_bytes) = Self::read_net_stats();
+        Self {
+            last_rx_bytes: rx_bytes,
+            last_tx_bytes: tx_bytes,
+            last_time: Instant::now(),
+            activity_level: 0.0,
+            max_activity: DecayingMax::new(Self::MIN_MAX_ACTIVITY),
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        let (rx_bytes, tx_bytes) = Self::read_net_stats();
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_time).as_secs_f64();
+
+        if elapsed > 0.0 {
+            let rx_delta = rx_bytes.saturating_sub(self.last_rx_bytes);
+            let tx_delta = tx_bytes.saturating_sub(self.last_tx_bytes);
+            let total_delta = rx_delta + tx_delta;
+
+            // Bytes per second
+            self.activity_level = total_delta as f64 / elapsed;
+            self.max_activity.update(self.activity_level);
+        }
+
+        self.last_rx_bytes = rx_bytes;
+        self.last_tx_bytes = tx_bytes;
+        self.last_time = now;
+    }
+
+    /// Returns network activity as a normalized value between 0.0 and 1.0
+    pub fn activity(&self) -> MetricValue {
+        self.max_activity.normalize(self.activity_level)
+    }
+
+    /// Check if an interface should be counted.
+    /// Loopback traffic is local IPC rather than real network activity.
+    fn is_counted_interface(interface: &str) -> bool {
+        interface != "lo"
+    }
+
+    /// Read total bytes received/transmitted from /proc/net/dev
+    fn read_net_stats() -> (u64, u64) {
+        match fs::read_to_string("/proc/net/dev") {
+            Ok(content) => Self::parse_net_stats(&content),
+            Err(_) => (0, 0),
+        }
+    }
+
+    /// Total bytes received/transmitted by the counted interfaces in /proc/net/dev contents
+    fn parse_net_stats(content: &str) -> (u64, u64) {
+        let mut total_rx = 0u64;
+        let mut total_tx = 0u64;
+
+        // The first two lines are column headers
+        for line in content.lines().skip(2) {
+            let Some((interface, counters)) = line.split_once(':') else {
+                continue;
+            };
+
+            if !Self::is_counted_interface(interface.trim()) {
+                continue;
+            }
+
+            let parts: Vec<&str> = counters.split_whitespace().collect();
+            if parts.len() < 9 {
+                continue;
+            }
+
+            // Field 0 is bytes received, field 8 is bytes transmitted
+            if let (Ok(rx), Ok(tx)) = (parts[0].parse::<u64>(), parts[8].parse::<u64>()) {
+                total_rx += rx;
+                total_tx += tx;
+            }
+        }
+
+        (total_rx, total_tx)
+    }
+}
+
+impl Default for NetworkMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricSource for NetworkMonitor {
+    fn name(&self) -> &str {
+        "network"
+    }
+
+    fn refresh(&mut self) {
+        NetworkMonitor::refresh(self);
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        vec![Metric::new("network.activity", self.activity())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NET_DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo: 9000000   12000    0    0    0     0          0         0  9000000   12000    0    0    0     0       0          0
+enp3s0: 1500000    2100    0    0    0     0          0        12   250000    1800    0    0    0     0       0          0
+ wlan0:    4000      30    0    0    0     0          0         0     6000      25    0    0    0     0       0          0
+";
+
+    #[test]
+    fn adds_up_interfaces_except_loopback() {
+        assert_eq!(
+            NetworkMonitor::parse_net_stats(NET_DEV),
+            (1_504_000, 256_000)
+        );
+    }
+
+    #[test]
+    fn skips_headers_and_malformed_lines() {
+        let content = format!(
+            "{}  tun0: 12 34\nbogus line\n eth1: x 0 0 0 0 0 0 0 5 0 0 0 0 0 0 0\n",
+            NET_DEV
+        );
+        assert_eq!(
+            NetworkMonitor::parse_net_stats(&content),
+            (1_504_000, 256_000)
+        );
+        assert_eq!(NetworkMonitor::parse_net_stats(""), (0, 0));
+    }
+}
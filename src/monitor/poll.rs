@@ -0,0 +1,106 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Runs something slow (a command, a scrape) on its own thread every `interval`, so it
+/// never stalls the update loop, and keeps its latest reading. The thread stops once
+/// the poller is dropped.
+pub(super) struct Poller {
+    /// Latest reading from the worker thread
+    reading: Arc<Mutex<Option<f64>>>,
+    /// Dropping this stops the worker thread
+    _stop: Sender<()>,
+}
+
+impl Poller {
+    /// Start calling `poll` every `interval`. It returns a reading, None when it has
+    /// none yet (the previous reading is kept), or an error, which is reported once
+    /// under `label` until the next success.
+    pub(super) fn spawn<F>(label: String, interval: Duration, mut poll: F) -> Self
+    where
+        F: FnMut() -> Result<Option<f64>, String> + Send + 'static,
+    {
+        let reading = Arc::new(Mutex::new(None));
+        let (stop, stopped) = mpsc::channel();
+
+        let worker_reading = Arc::clone(&reading);
+        thread::spawn(move || {
+            let mut last_error = None;
+            loop {
+                match poll() {
+                    Ok(value) => {
+                        if let (Some(value), Ok(mut reading)) = (value, worker_reading.lock()) {
+                            *reading = Some(value);
+                        }
+                        last_error = None;
+                    }
+                    Err(e) => {
+                        // Only report a problem once rather than on every run
+                        if last_error.as_ref() != Some(&e) {
+                            eprintln!("Warning: {}: {}", label, e);
+                        }
+                        last_error = Some(e);
+                    }
+                }
+
+                match stopped.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => break,
+                }
+            }
+        });
+
+        Self {
+            reading,
+            _stop: stop,
+        }
+    }
+
+    /// The latest reading, if there has been one
+    pub(super) fn latest(&self) -> Option<f64> {
+        self.reading.lock().ok().and_then(|r| *r)
+    }
+}
+
+/// Map a reading onto 0.0-1.0 between `min` and `max` (which may be below `min`)
+pub(super) fn scale(reading: f64, min: f64, max: f64) -> f64 {
+    let range = max - min;
+    if range == 0.0 {
+        return 0.0;
+    }
+    (reading - min) / range
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn scale_maps_min_and_max() {
+        assert_eq!(scale(15.0, 10.0, 20.0), 0.5);
+        assert_eq!(scale(12.0, 20.0, 10.0), 0.8);
+        assert_eq!(scale(15.0, 10.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn keeps_the_last_reading_through_gaps_and_errors() {
+        let mut runs = 0;
+        let poller = Poller::spawn("test".to_string(), Duration::from_millis(1), move || {
+            runs += 1;
+            match runs {
+                1 => Ok(None),
+                2 => Ok(Some(7.0)),
+                _ => Err("failed".to_string()),
+            }
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while poller.latest().is_none() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(poller.latest(), Some(7.0));
+    }
+}
@@ -0,0 +1,283 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{DecayingMax, Metric, MetricSource, MetricValue};
+
+/// A change in external power, reported once when it happens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerEvent {
+    /// External power was connected
+    Online,
+    /// External power was disconnected
+    Offline,
+}
+
+/// Monitors batteries and external power by reading /sys/class/power_supply
+pub struct PowerMonitor {
+    root: PathBuf,
+    /// Whether external power is connected (None until first read or without any supply)
+    online: Option<bool>,
+    /// Average battery charge between 0.0 and 1.0 (None without a battery)
+    battery_level: Option<f64>,
+    /// Combined battery discharge rate in watts (0.0 while charging or on AC)
+    discharge_watts: f64,
+    /// Maximum observed discharge rate for normalization
+    max_discharge: DecayingMax,
+    /// Power event from the latest refresh, if any
+    event: Option<PowerEvent>,
+}
+
+impl PowerMonitor {
+    const POWER_SUPPLY_ROOT: &'static str = "/sys/class/power_supply";
+
+    /// Minimum discharge threshold (watts) to avoid division by very small numbers
+    const MIN_MAX_DISCHARGE: f64 = 5.0;
+
+    /// Metrics published as events rather than levels
+    pub const EVENTS: [&'static str; 2] = ["power.connected", "power.disconnected"];
+
+    pub fn new() -> Self {
+        Self::with_root(Self::POWER_SUPPLY_ROOT)
+    }
+
+    /// Create a monitor that reads supplies below `root` instead of /sys/class/power_supply
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        let mut monitor = Self {
+            root: root.into(),
+            online: None,
+            battery_level: None,
+            discharge_watts: 0.0,
+            max_discharge: DecayingMax::new(Self::MIN_MAX_DISCHARGE),
+            event: None,
+        };
+        // Establish the initial online state so start-up doesn't count as an event
+        monitor.refresh();
+        monitor.event = None;
+        monitor
+    }
+
+    pub fn refresh(&mut self) {
+        let mut mains_online: Option<bool> = None;
+        let mut any_discharging = false;
+        let mut capacities = Vec::new();
+        let mut discharge_watts = 0.0;
+
+        let mut supplies: Vec<PathBuf> = fs::read_dir(&self.root)
+            .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+            .unwrap_or_default();
+        supplies.sort();
+
+        for supply in supplies {
+            match read_string(&supply.join("type")).as_deref() {
+                Some("Battery") => {
+                    if let Some(capacity) = read_number(&supply.join("capacity")) {
+                        capacities.push(capacity / 100.0);
+                    }
+
+                    if read_string(&supply.join("status")).as_deref() == Some("Discharging") {
+                        any_discharging = true;
+                        discharge_watts += Self::read_power_watts(&supply);
+                    }
+                }
+                // Mains, USB, USB_C, Wireless...
+                Some(_) => {
+                    if let Some(online) = read_number(&supply.join("online")) {
+                        mains_online = Some(mains_online.unwrap_or(false) || online != 0.0);
+                    }
+                }
+                None => {}
+            }
+        }
+
+        // Without an AC adapter entry, infer external power from the battery status
+        let online = mains_online.or(if capacities.is_empty() {
+            None
+        } else {
+            Some(!any_discharging)
+        });
+
+        self.event = match (self.online, online) {
+            (Some(false), Some(true)) => Some(PowerEvent::Online),
+            (Some(true), Some(false)) => Some(PowerEvent::Offline),
+            _ => None,
+        };
+        self.online = online;
+
+        self.battery_level = if capacities.is_empty() {
+            None
+        } else {
+            Some(capacities.iter().sum::<f64>() / capacities.len() as f64)
+        };
+
+        self.discharge_watts = discharge_watts;
+        self.max_discharge.update(self.discharge_watts);
+    }
+
+    /// Read a battery's power draw in watts, from `power_now` or `current_now` * `voltage_now`
+    fn read_power_watts(battery: &Path) -> f64 {
+        // power_now is in microwatts
+        if let Some(power) = read_number(&battery.join("power_now")) {
+            return power.abs() / 1_000_000.0;
+        }
+
+        // current_now is in microamps, voltage_now in microvolts
+        match (
+            read_number(&battery.join("current_now")),
+            read_number(&battery.join("voltage_now")),
+        ) {
+            (Some(current), Some(voltage)) => (current * voltage).abs() / 1_000_000_000_000.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Returns the battery charge level between 0.0 and 1.0 (0.0 without a battery)
+    pub fn battery_level(&self) -> MetricValue {
+        MetricValue::new(self.battery_level.unwrap_or(0.0))
+    }
+
+    /// Returns how far the battery has run down between 0.0 and 1.0 (0.0 without a battery)
+    pub fn battery_depletion(&self) -> MetricValue {
+        MetricValue::new(self.battery_level.map_or(0.0, |level| 1.0 - level))
+    }
+
+    /// Returns the battery discharge rate as a normalized value between 0.0 and 1.0
+    pub fn discharge_rate(&self) -> MetricValue {
+        self.max_discharge.normalize(self.discharge_watts)
+    }
+
+    /// Returns the power event detected by the latest refresh, if any
+    pub fn event(&self) -> Option<PowerEvent> {
+        self.event
+    }
+}
+
+impl Default for PowerMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricSource for PowerMonitor {
+    fn name(&self) -> &str {
+        "power"
+    }
+
+    fn refresh(&mut self) {
+        PowerMonitor::refresh(self);
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        let event =
+            |e: PowerEvent| MetricValue::new(if self.event() == Some(e) { 1.0 } else { 0.0 });
+        vec![
+            Metric::new("battery.level", self.battery_level()),
+            Metric::new("battery.depletion", self.battery_depletion()),
+            Metric::new("battery.drain", self.discharge_rate()),
+            Metric::new(Self::EVENTS[0], event(PowerEvent::Online)),
+            Metric::new(Self::EVENTS[1], event(PowerEvent::Offline)),
+        ]
+    }
+}
+
+fn read_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_number(path: &Path) -> Option<f64> {
+    read_string(path).and_then(|s| s.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::write_files;
+
+    fn value(monitor: &PowerMonitor, id: &str) -> f64 {
+        monitor
+            .values()
+            .iter()
+            .find(|m| m.id == id)
+            .map(|m| m.value.get())
+            .unwrap()
+    }
+
+    #[test]
+    fn reads_battery_charge_and_drain() {
+        let root = tempfile::tempdir().unwrap();
+        write_files(
+            root.path(),
+            &[
+                ("AC/type", "Mains\n"),
+                ("AC/online", "0\n"),
+                ("BAT0/type", "Battery\n"),
+                ("BAT0/capacity", "80\n"),
+                ("BAT0/status", "Discharging\n"),
+                ("BAT0/current_now", "500000\n"),
+                ("BAT0/voltage_now", "5000000\n"),
+            ],
+        );
+        let monitor = PowerMonitor::with_root(root.path());
+
+        assert!((monitor.battery_level().get() - 0.8).abs() < 1e-9);
+        assert!((value(&monitor, "battery.depletion") - 0.2).abs() < 1e-9);
+        // 0.5 A at 5 V is 2.5 W, against the 5 W minimum range
+        assert!((monitor.discharge_rate().get() - 0.5).abs() < 1e-9);
+        assert_eq!(monitor.event(), None);
+    }
+
+    #[test]
+    fn reports_plugging_in_once() {
+        let root = tempfile::tempdir().unwrap();
+        write_files(root.path(), &[("AC/type", "Mains\n"), ("AC/online", "0\n")]);
+        let mut monitor = PowerMonitor::with_root(root.path());
+
+        write_files(root.path(), &[("AC/online", "1\n")]);
+        monitor.refresh();
+        assert_eq!(monitor.event(), Some(PowerEvent::Online));
+        assert_eq!(value(&monitor, "power.connected"), 1.0);
+        assert_eq!(value(&monitor, "power.disconnected"), 0.0);
+
+        monitor.refresh();
+        assert_eq!(monitor.event(), None);
+    }
+
+    #[test]
+    fn infers_external_power_from_the_battery_without_an_adapter() {
+        let root = tempfile::tempdir().unwrap();
+        write_files(
+            root.path(),
+            &[
+                ("BAT0/type", "Battery\n"),
+                ("BAT0/capacity", "50\n"),
+                ("BAT0/status", "Charging\n"),
+            ],
+        );
+        let mut monitor = PowerMonitor::with_root(root.path());
+
+        write_files(root.path(), &[("BAT0/status", "Discharging\n")]);
+        monitor.refresh();
+        assert_eq!(monitor.event(), Some(PowerEvent::Offline));
+    }
+
+    #[test]
+    fn desktop_without_a_battery_is_not_depleted() {
+        let root = tempfile::tempdir().unwrap();
+        write_files(root.path(), &[("AC/type", "Mains\n"), ("AC/online", "1\n")]);
+        let monitor = PowerMonitor::with_root(root.path());
+
+        assert_eq!(monitor.battery_level().get(), 0.0);
+        assert_eq!(value(&monitor, "battery.depletion"), 0.0);
+    }
+
+    #[test]
+    fn missing_power_supply_reads_zero() {
+        let root = tempfile::tempdir().unwrap();
+        let mut monitor = PowerMonitor::with_root(root.path().join("missing"));
+        monitor.refresh();
+
+        assert_eq!(monitor.battery_level().get(), 0.0);
+        assert_eq!(value(&monitor, "battery.depletion"), 0.0);
+        assert_eq!(monitor.discharge_rate().get(), 0.0);
+        assert_eq!(monitor.event(), None);
+    }
+}
@@ -0,0 +1,308 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use super::{Metric, MetricSource, MetricValue};
+
+/// Which Pressure Stall Information figure a channel follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PressureStat {
+    /// Share of time at least one task stalled, averaged over 10 seconds
+    #[default]
+    SomeAvg10,
+    /// Share of time all non-idle tasks stalled, averaged over 10 seconds
+    FullAvg10,
+    /// Share of time at least one task stalled since the last refresh
+    SomeTotal,
+    /// Share of time all non-idle tasks stalled since the last refresh
+    FullTotal,
+}
+
+impl PressureStat {
+    pub const ALL: [Self; 4] = [
+        Self::SomeAvg10,
+        Self::FullAvg10,
+        Self::SomeTotal,
+        Self::FullTotal,
+    ];
+
+    /// Metric ID of this figure for a resource ("cpu", "memory" or "io"), e.g. "pressure.io.some"
+    pub fn metric_id(&self, resource: &str) -> String {
+        let stat = match self {
+            Self::SomeAvg10 => "some",
+            Self::FullAvg10 => "full",
+            Self::SomeTotal => "some_total",
+            Self::FullTotal => "full_total",
+        };
+        format!("pressure.{}.{}", resource, stat)
+    }
+
+    pub fn from_name(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "some" | "some_avg10" => Some(Self::SomeAvg10),
+            "full" | "full_avg10" => Some(Self::FullAvg10),
+            "some_total" => Some(Self::SomeTotal),
+            "full_total" => Some(Self::FullTotal),
+            _ => None,
+        }
+    }
+}
+
+/// Stall figures for a single resource (each 0.0 to 1.0)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResourcePressure {
+    pub some_avg10: MetricValue,
+    pub full_avg10: MetricValue,
+    /// Derived from the `total` counter delta since the last refresh
+    pub some_total: MetricValue,
+    /// Derived from the `total` counter delta since the last refresh
+    pub full_total: MetricValue,
+}
+
+impl ResourcePressure {
+    pub fn get(&self, stat: PressureStat) -> MetricValue {
+        match stat {
+            PressureStat::SomeAvg10 => self.some_avg10,
+            PressureStat::FullAvg10 => self.full_avg10,
+            PressureStat::SomeTotal => self.some_total,
+            PressureStat::FullTotal => self.full_total,
+        }
+    }
+}
+
+/// Pressure Stall Information for CPU, memory and IO
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PressureMetrics {
+    pub cpu: ResourcePressure,
+    pub memory: ResourcePressure,
+    pub io: ResourcePressure,
+}
+
+/// One `some` or `full` line of a pressure file
+#[derive(Debug, Clone, Copy, Default)]
+struct PressureLine {
+    /// Percentage of time stalled over the last 10 seconds
+    avg10: f64,
+    /// Total stall time in microseconds
+    total: u64,
+}
+
+/// Parsed contents of a pressure file
+#[derive(Debug, Clone, Copy, Default)]
+struct PressureFile {
+    some: PressureLine,
+    full: PressureLine,
+}
+
+/// Tracks one resource's pressure file between refreshes
+struct ResourceTracker {
+    path: PathBuf,
+    last: PressureFile,
+    current: ResourcePressure,
+}
+
+impl ResourceTracker {
+    fn new(path: PathBuf) -> Self {
+        let last = read_pressure_file(&path).unwrap_or_default();
+        Self {
+            path,
+            last,
+            current: ResourcePressure::default(),
+        }
+    }
+
+    fn refresh(&mut self, elapsed_us: f64) {
+        let Some(file) = read_pressure_file(&self.path) else {
+            self.current = ResourcePressure::default();
+            return;
+        };
+
+        let share = |now: u64, before: u64| {
+            if elapsed_us > 0.0 {
+                MetricValue::new(now.saturating_sub(before) as f64 / elapsed_us)
+            } else {
+                MetricValue::new(0.0)
+            }
+        };
+
+        self.current = ResourcePressure {
+            some_avg10: MetricValue::new(file.some.avg10 / 100.0),
+            full_avg10: MetricValue::new(file.full.avg10 / 100.0),
+            some_total: share(file.some.total, self.last.some.total),
+            full_total: share(file.full.total, self.last.full.total),
+        };
+        self.last = file;
+    }
+}
+
+/// Monitors Pressure Stall Information from /proc/pressure
+pub struct PressureMonitor {
+    cpu: ResourceTracker,
+    memory: ResourceTracker,
+    io: ResourceTracker,
+    last_time: Instant,
+}
+
+impl PressureMonitor {
+    const PRESSURE_ROOT: &'static str = "/proc/pressure";
+
+    pub fn new() -> Self {
+        Self::with_root(Self::PRESSURE_ROOT)
+    }
+
+    /// Create a monitor that reads pressure files below `root` instead of /proc/pressure
+    pub fn with_root(root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        Self {
+            cpu: ResourceTracker::new(root.join("cpu")),
+            memory: ResourceTracker::new(root.join("memory")),
+            io: ResourceTracker::new(root.join("io")),
+            last_time: Instant::now(),
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        let now = Instant::now();
+        let elapsed_us = now.duration_since(self.last_time).as_secs_f64() * 1_000_000.0;
+
+        self.cpu.refresh(elapsed_us);
+        self.memory.refresh(elapsed_us);
+        self.io.refresh(elapsed_us);
+
+        self.last_time = now;
+    }
+
+    /// Returns the latest pressure figures
+    pub fn pressure(&self) -> PressureMetrics {
+        PressureMetrics {
+            cpu: self.cpu.current,
+            memory: self.memory.current,
+            io: self.io.current,
+        }
+    }
+}
+
+impl Default for PressureMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricSource for PressureMonitor {
+    fn name(&self) -> &str {
+        "pressure"
+    }
+
+    fn refresh(&mut self) {
+        PressureMonitor::refresh(self);
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        let pressure = self.pressure();
+        let mut values = Vec::new();
+        for (resource, figures) in [
+            ("cpu", pressure.cpu),
+            ("memory", pressure.memory),
+            ("io", pressure.io),
+        ] {
+            for stat in PressureStat::ALL {
+                values.push(Metric::new(&stat.metric_id(resource), figures.get(stat)));
+            }
+        }
+        values
+    }
+}
+
+/// Parse a pressure file, e.g.
+/// `some avg10=0.12 avg60=0.05 avg300=0.01 total=123456`
+/// `full avg10=0.00 avg60=0.00 avg300=0.00 total=0`
+fn read_pressure_file(path: &Path) -> Option<PressureFile> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut file = PressureFile::default();
+
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let target = match fields.next() {
+            Some("some") => &mut file.some,
+            Some("full") => &mut file.full,
+            _ => continue,
+        };
+
+        for field in fields {
+            match field.split_once('=') {
+                Some(("avg10", v)) => target.avg10 = v.parse().unwrap_or(0.0),
+                Some(("total", v)) => target.total = v.parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+
+    Some(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDLE: &str = "some avg10=0.00 avg60=0.00 avg300=0.00 total=1000000\n\
+                        full avg10=0.00 avg60=0.00 avg300=0.00 total=500000\n";
+    const STALLED: &str = "some avg10=25.50 avg60=10.00 avg300=2.00 total=1500000\n\
+                           full avg10=5.00 avg60=1.00 avg300=0.10 total=600000\n";
+
+    #[test]
+    fn parses_some_and_full_lines() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("io"), STALLED).unwrap();
+        let file = read_pressure_file(&root.path().join("io")).unwrap();
+
+        assert_eq!(file.some.avg10, 25.5);
+        assert_eq!(file.some.total, 1_500_000);
+        assert_eq!(file.full.avg10, 5.0);
+        assert_eq!(file.full.total, 600_000);
+    }
+
+    #[test]
+    fn normalizes_averages_and_total_deltas() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("memory");
+        fs::write(&path, IDLE).unwrap();
+        let mut tracker = ResourceTracker::new(path.clone());
+
+        fs::write(&path, STALLED).unwrap();
+        // Half a second stalled by some tasks and 0.1s by all, over one second
+        tracker.refresh(1_000_000.0);
+
+        let current = tracker.current;
+        assert!((current.some_avg10.get() - 0.255).abs() < 1e-9);
+        assert!((current.full_avg10.get() - 0.05).abs() < 1e-9);
+        assert!((current.some_total.get() - 0.5).abs() < 1e-9);
+        assert!((current.full_total.get() - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cpu_without_a_full_line_reads_zero_full() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(
+            root.path().join("cpu"),
+            "some avg10=40.00 avg60=0.00 avg300=0.00 total=0\n",
+        )
+        .unwrap();
+        let mut monitor = PressureMonitor::with_root(root.path());
+        monitor.refresh();
+
+        let cpu = monitor.pressure().cpu;
+        assert!((cpu.some_avg10.get() - 0.4).abs() < 1e-9);
+        assert_eq!(cpu.full_avg10.get(), 0.0);
+    }
+
+    #[test]
+    fn missing_psi_reads_zero() {
+        let root = tempfile::tempdir().unwrap();
+        let mut monitor = PressureMonitor::with_root(root.path().join("missing"));
+        monitor.refresh();
+
+        let values = monitor.values();
+        assert_eq!(values.len(), 12);
+        assert!(values.iter().all(|m| m.value.get() == 0.0));
+    }
+}
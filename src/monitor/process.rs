@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System};
+
+use super::disk::glob_match;
+use super::{DecayingMax, Metric, MetricSource, MetricValue};
+
+/// Which figure the Process channel follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProcessSource {
+    /// Combined CPU usage of the watched processes
+    #[default]
+    Cpu,
+    /// Combined resident memory of the watched processes
+    Memory,
+    /// Combined disk reads and writes of the watched processes
+    Io,
+}
+
+impl ProcessSource {
+    /// ID of the metric this figure is published as
+    pub fn metric_id(&self) -> &'static str {
+        match self {
+            Self::Cpu => "process.cpu",
+            Self::Memory => "process.memory",
+            Self::Io => "process.io",
+        }
+    }
+
+    pub fn from_name(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "memory" | "rss" => Self::Memory,
+            "io" | "disk" => Self::Io,
+            _ => Self::Cpu,
+        }
+    }
+}
+
+/// Which processes to watch. A process is watched if it matches any of the criteria.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessSelector {
+    /// Process names or glob patterns (e.g. "rustc", "cc1*")
+    pub names: Vec<String>,
+    /// Specific process IDs
+    pub pids: Vec<u32>,
+    /// Process IDs that are watched together with all their descendants
+    pub parents: Vec<u32>,
+}
+
+impl ProcessSelector {
+    /// Select every process with the given name (or glob)
+    pub fn by_name(name: &str) -> Self {
+        Self {
+            names: vec![name.to_string()],
+            ..Self::default()
+        }
+    }
+
+    /// Whether nothing is selected (process monitoring is off)
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.pids.is_empty() && self.parents.is_empty()
+    }
+
+    /// Check if a process is selected, following its ancestry through `parent_of` for `parents`
+    fn selects(&self, name: &str, pid: u32, parent_of: impl Fn(u32) -> Option<u32>) -> bool {
+        if self.names.iter().any(|pattern| glob_match(pattern, name)) {
+            return true;
+        }
+
+        if self.pids.contains(&pid) || self.parents.contains(&pid) {
+            return true;
+        }
+
+        if self.parents.is_empty() {
+            return false;
+        }
+
+        // Walk up the tree (bounded, in case of a stale parent loop)
+        let mut parent = parent_of(pid);
+        for _ in 0..64 {
+            let Some(ppid) = parent else {
+                return false;
+            };
+            if self.parents.contains(&ppid) {
+                return true;
+            }
+            parent = parent_of(ppid);
+        }
+
+        false
+    }
+
+    /// Check if a process in `processes` is selected
+    fn selects_process(&self, process: &Process, processes: &HashMap<Pid, Process>) -> bool {
+        let parent_of = |pid: u32| {
+            processes
+                .get(&Pid::from_u32(pid))
+                .and_then(|p| p.parent())
+                .map(|p| p.as_u32())
+        };
+        self.selects(
+            &process.name().to_string_lossy(),
+            process.pid().as_u32(),
+            parent_of,
+        )
+    }
+}
+
+/// What one process uses, to be added up over the watched processes
+#[derive(Debug, Clone, Copy, Default)]
+struct ProcessUsage {
+    /// CPU usage in percent of one core
+    cpu_percent: f64,
+    rss_bytes: u64,
+    /// Bytes read and written since the last refresh
+    io_bytes: u64,
+}
+
+impl ProcessUsage {
+    fn of(process: &Process) -> Self {
+        let disk = process.disk_usage();
+        Self {
+            cpu_percent: process.cpu_usage() as f64,
+            rss_bytes: process.memory(),
+            io_bytes: disk.read_bytes + disk.written_bytes,
+        }
+    }
+}
+
+/// Monitors the combined CPU, memory and IO of a group of processes
+pub struct ProcessMonitor {
+    system: System,
+    selector: ProcessSelector,
+    core_count: usize,
+    last_time: Instant,
+    /// Combined CPU usage between 0.0 and 1.0 of all cores
+    cpu_level: f64,
+    /// Combined resident memory between 0.0 and 1.0 of total memory
+    memory_level: f64,
+    /// Bytes read and written per second at last measurement
+    io_level: f64,
+    /// Maximum observed IO rate for normalization
+    max_io: DecayingMax,
+    /// Watched processes found by the last scan of every process. Only these are
+    /// refreshed until the next scan.
+    watched: Vec<Pid>,
+    /// When every process was last scanned
+    last_scan: Option<Instant>,
+}
+
+impl ProcessMonitor {
+    /// Minimum IO threshold (bytes per second) to avoid division by very small numbers
+    const MIN_MAX_IO: f64 = 512_000.0;
+
+    /// How often to look through every process for newly started watched ones
+    const SCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+    pub fn new(selector: ProcessSelector, core_count: usize) -> Self {
+        let mut system = System::new();
+        system.refresh_memory();
+
+        let mut monitor = Self {
+            system,
+            selector,
+            core_count: core_count.max(1),
+            last_time: Instant::now(),
+            cpu_level: 0.0,
+            memory_level: 0.0,
+            io_level: 0.0,
+            max_io: DecayingMax::new(Self::MIN_MAX_IO),
+            watched: Vec::new(),
+            last_scan: None,
+        };
+        // Baseline for CPU and IO deltas
+        if !monitor.selector.is_empty() {
+            monitor.scan();
+        }
+        monitor
+    }
+
+    /// Replace the set of watched processes
+    pub fn set_selector(&mut self, selector: ProcessSelector) {
+        self.selector = selector;
+        self.watched.clear();
+        self.last_scan = None;
+        self.cpu_level = 0.0;
+        self.memory_level = 0.0;
+        self.io_level = 0.0;
+    }
+
+    /// Returns the set of watched processes
+    pub fn selector(&self) -> &ProcessSelector {
+        &self.selector
+    }
+
+    fn refresh_kind() -> ProcessRefreshKind {
+        ProcessRefreshKind::new()
+            .with_cpu()
+            .with_memory()
+            .with_disk_usage()
+    }
+
+    fn refresh_processes(&mut self) {
+        self.system
+            .refresh_processes_specifics(ProcessesToUpdate::All, Self::refresh_kind());
+    }
+
+    /// Refresh every process and pick out the watched ones
+    fn scan(&mut self) {
+        self.refresh_processes();
+        self.last_scan = Some(Instant::now());
+
+        let processes = self.system.processes();
+        // Threads are listed as processes too; only count each process once
+        self.watched = processes
+            .values()
+            .filter(|p| p.thread_kind().is_none() && self.selector.selects_process(p, processes))
+            .map(|p| p.pid())
+            .collect();
+    }
+
+    pub fn refresh(&mut self) {
+        // Enumerating every process is relatively expensive, so skip it when nothing is watched
+        if self.selector.is_empty() {
+            return;
+        }
+
+        // Once watched processes are found, only they are refreshed, with a full scan now
+        // and then for new ones. A watched process exiting calls for a scan right away.
+        let scan_due = match self.last_scan {
+            Some(t) => t.elapsed() >= Self::SCAN_INTERVAL,
+            None => true,
+        };
+        let all_refreshed = !self.watched.is_empty()
+            && !scan_due
+            && self.system.refresh_processes_specifics(
+                ProcessesToUpdate::Some(&self.watched),
+                Self::refresh_kind(),
+            ) >= self.watched.len();
+        if !all_refreshed {
+            self.scan();
+        }
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_time).as_secs_f64();
+        self.last_time = now;
+
+        let processes = self.system.processes();
+        let usages: Vec<ProcessUsage> = self
+            .watched
+            .iter()
+            .filter_map(|pid| processes.get(pid))
+            .map(ProcessUsage::of)
+            .collect();
+        let total_memory = self.system.total_memory();
+        self.update(&usages, total_memory, elapsed);
+    }
+
+    /// Add up what the watched processes used over the last `elapsed` seconds
+    fn update(&mut self, usages: &[ProcessUsage], total_memory: u64, elapsed: f64) {
+        let cpu_percent: f64 = usages.iter().map(|u| u.cpu_percent).sum();
+        let rss_bytes: u64 = usages.iter().map(|u| u.rss_bytes).sum();
+        let io_bytes: u64 = usages.iter().map(|u| u.io_bytes).sum();
+
+        self.cpu_level = cpu_percent / 100.0 / self.core_count as f64;
+
+        self.memory_level = if total_memory == 0 {
+            0.0
+        } else {
+            rss_bytes as f64 / total_memory as f64
+        };
+
+        if elapsed > 0.0 {
+            // Bytes per second
+            self.io_level = io_bytes as f64 / elapsed;
+            self.max_io.update(self.io_level);
+        }
+    }
+
+    /// Returns combined CPU usage of the watched processes across all cores
+    pub fn cpu_usage(&self) -> MetricValue {
+        MetricValue::new(self.cpu_level)
+    }
+
+    /// Returns combined resident memory of the watched processes as a share of total memory
+    pub fn memory_usage(&self) -> MetricValue {
+        MetricValue::new(self.memory_level)
+    }
+
+    /// Returns combined disk IO of the watched processes as a normalized value
+    pub fn io_activity(&self) -> MetricValue {
+        self.max_io.normalize(self.io_level)
+    }
+
+    /// Returns the names of the busiest processes by CPU usage (all instances of a name combined)
+    pub fn top_process_names(&mut self, count: usize) -> Vec<String> {
+        // When nothing is watched the list isn't refreshed every tick, so refresh it here.
+        // CPU usage is then averaged since the previous call. Otherwise it is as of the
+        // last scan of every process.
+        if self.selector.is_empty() {
+            self.refresh_processes();
+        }
+
+        let mut by_name: HashMap<String, f32> = HashMap::new();
+        for process in self
+            .system
+            .processes()
+            .values()
+            .filter(|p| p.thread_kind().is_none())
+        {
+            let name = process.name().to_string_lossy().into_owned();
+            *by_name.entry(name).or_default() += process.cpu_usage();
+        }
+
+        let mut top: Vec<(String, f32)> = by_name.into_iter().collect();
+        top.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.into_iter().take(count).map(|(name, _)| name).collect()
+    }
+}
+
+impl MetricSource for ProcessMonitor {
+    fn name(&self) -> &str {
+        "process"
+    }
+
+    fn refresh(&mut self) {
+        ProcessMonitor::refresh(self);
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        vec![
+            Metric::new("process.cpu", self.cpu_usage()),
+            Metric::new("process.memory", self.memory_usage()),
+            Metric::new("process.io", self.io_activity()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parent of every process in a small tree: 1 <- 10 <- 100, and 2
+    fn parent_of(pid: u32) -> Option<u32> {
+        match pid {
+            10 => Some(1),
+            100 => Some(10),
+            1 | 2 => None,
+            _ => Some(2),
+        }
+    }
+
+    #[test]
+    fn processes_are_selected_by_name_glob_or_pid() {
+        let selector = ProcessSelector {
+            names: vec!["rustc".to_string(), "cc1*".to_string()],
+            pids: vec![42],
+            parents: Vec::new(),
+        };
+        assert!(selector.selects("rustc", 7, parent_of));
+        assert!(selector.selects("cc1plus", 7, parent_of));
+        assert!(!selector.selects("rustdoc", 7, parent_of));
+        assert!(!selector.selects("xcc1", 7, parent_of));
+        assert!(selector.selects("bash", 42, parent_of));
+        assert!(!selector.selects("bash", 43, parent_of));
+    }
+
+    #[test]
+    fn parents_select_their_descendants() {
+        let selector = ProcessSelector {
+            parents: vec![10],
+            ..ProcessSelector::default()
+        };
+        assert!(selector.selects("make", 10, parent_of));
+        assert!(selector.selects("cc1", 100, parent_of));
+        assert!(!selector.selects("init", 1, parent_of));
+        assert!(!selector.selects("cc1", 3, parent_of));
+
+        // A loop in stale parent links gives up rather than spinning
+        let looped = |pid: u32| Some(pid + 1);
+        assert!(!selector.selects("cc1", 11, looped));
+    }
+
+    #[test]
+    fn usage_is_added_up_over_the_watched_processes() {
+        let mut monitor = ProcessMonitor::new(ProcessSelector::default(), 4);
+        let usages = [
+            ProcessUsage {
+                cpu_percent: 150.0,
+                rss_bytes: 1000,
+                io_bytes: 600_000,
+            },
+            ProcessUsage {
+                cpu_percent: 50.0,
+                rss_bytes: 3000,
+                io_bytes: 400_000,
+            },
+        ];
+        monitor.update(&usages, 8000, 2.0);
+
+        // 200% of one core out of four
+        assert_eq!(monitor.cpu_usage().get(), 0.5);
+        assert_eq!(monitor.memory_usage().get(), 0.5);
+        // 500 kB/s, just under the 512 kB/s floor of the IO max
+        assert!((monitor.io_activity().get() - 500_000.0 / 512_000.0).abs() < 1e-9);
+
+        monitor.update(&[], 0, 1.0);
+        assert_eq!(monitor.cpu_usage().get(), 0.0);
+        assert_eq!(monitor.memory_usage().get(), 0.0);
+        assert_eq!(monitor.io_activity().get(), 0.0);
+    }
+}
@@ -0,0 +1,427 @@
+use regex::Regex;
+use std::time::{Duration, Instant};
+
+use super::poll::{scale, Poller};
+use super::{Metric, MetricSource, MetricValue};
+
+/// Label names and values of a sample
+type Labels = Vec<(String, String)>;
+
+/// Label name, operator and value of each pair between braces
+type LabelPairs = Vec<(String, &'static str, String)>;
+
+/// How a series' label is compared
+#[derive(Debug, Clone)]
+pub enum LabelMatcher {
+    /// `label="value"`
+    Equal(String, String),
+    /// `label!="value"`
+    NotEqual(String, String),
+    /// `label=~"regex"`
+    Matches(String, Regex),
+    /// `label!~"regex"`
+    NotMatches(String, Regex),
+}
+
+impl LabelMatcher {
+    fn accepts(&self, labels: &[(String, String)]) -> bool {
+        // A missing label compares as empty, as in PromQL
+        let value_of = |name: &str| {
+            labels
+                .iter()
+                .find(|(l, _)| l == name)
+                .map(|(_, v)| v.as_str())
+                .unwrap_or("")
+        };
+
+        match self {
+            Self::Equal(name, value) => value_of(name) == value,
+            Self::NotEqual(name, value) => value_of(name) != value,
+            Self::Matches(name, regex) => regex.is_match(value_of(name)),
+            Self::NotMatches(name, regex) => !regex.is_match(value_of(name)),
+        }
+    }
+}
+
+/// A series selector such as `http_requests_total{job="api",code=~"5.."}`
+#[derive(Debug, Clone)]
+pub struct SeriesSelector {
+    pub name: String,
+    pub labels: Vec<LabelMatcher>,
+}
+
+impl SeriesSelector {
+    /// Parse a selector in PromQL syntax (metric name with optional label matchers)
+    pub fn parse(selector: &str) -> Result<Self, String> {
+        let selector = selector.trim();
+        let (name, rest) = match selector.find('{') {
+            Some(brace) => (&selector[..brace], &selector[brace..]),
+            None => (selector, ""),
+        };
+
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("no metric name".to_string());
+        }
+
+        let mut labels = Vec::new();
+        if !rest.is_empty() {
+            let (pairs, remainder) = parse_labels(rest).ok_or("malformed label matchers")?;
+            if !remainder.trim().is_empty() {
+                return Err(format!("unexpected '{}'", remainder.trim()));
+            }
+            for (label, op, value) in pairs {
+                let anchored = || {
+                    Regex::new(&format!("^(?:{})$", value))
+                        .map_err(|e| format!("invalid regex for '{}': {}", label, e))
+                };
+                labels.push(match op {
+                    "=" => LabelMatcher::Equal(label, value),
+                    "!=" => LabelMatcher::NotEqual(label, value),
+                    "=~" => LabelMatcher::Matches(label.clone(), anchored()?),
+                    _ => LabelMatcher::NotMatches(label.clone(), anchored()?),
+                });
+            }
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            labels,
+        })
+    }
+
+    fn selects(&self, name: &str, labels: &[(String, String)]) -> bool {
+        name == self.name && self.labels.iter().all(|m| m.accepts(labels))
+    }
+}
+
+/// An HTTP endpoint in the Prometheus text format, and the series to follow from it
+#[derive(Debug, Clone)]
+pub struct ScrapeConfig {
+    /// Name of the metric, published as `prometheus.<name>`
+    pub name: String,
+    /// URL of the `/metrics` endpoint (plain HTTP)
+    pub url: String,
+    /// Series to follow. When several series match, their values are added up.
+    pub selector: SeriesSelector,
+    /// Follow the per-second rate of the value instead of the value, for counters
+    pub rate: bool,
+    /// Value (or rate) that maps to 0.0
+    pub min: f64,
+    /// Value (or rate) that maps to 1.0 (may be below `min` to invert the metric)
+    pub max: f64,
+    /// Time between scrapes
+    pub interval: Duration,
+    /// Time after which a scrape is abandoned
+    pub timeout: Duration,
+}
+
+impl ScrapeConfig {
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+
+    pub fn new(name: &str, url: &str, selector: SeriesSelector) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            selector,
+            rate: false,
+            min: 0.0,
+            max: 1.0,
+            interval: Self::DEFAULT_INTERVAL,
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// ID of the metric this series is published as
+    pub fn metric_id(&self) -> String {
+        format!("prometheus.{}", self.name)
+    }
+
+    /// Sum of every selected series in a scrape, or None if nothing matched
+    fn select(&self, exposition: &str) -> Option<f64> {
+        let mut total = None;
+        for line in exposition.lines() {
+            let Some((name, labels, value)) = parse_sample(line) else {
+                continue;
+            };
+            if value.is_finite() && self.selector.selects(name, &labels) {
+                *total.get_or_insert(0.0) += value;
+            }
+        }
+        total
+    }
+}
+
+/// Follows a series scraped from a Prometheus/OpenMetrics endpoint.
+///
+/// Scrapes happen on their own thread so a slow exporter never stalls the update loop;
+/// `refresh` only picks up the latest reading. When a scrape fails or the series is
+/// missing, the previous reading is kept.
+pub struct PrometheusSource {
+    config: ScrapeConfig,
+    metric_id: String,
+    poller: Poller,
+    level: f64,
+}
+
+impl PrometheusSource {
+    pub fn new(config: ScrapeConfig) -> Self {
+        let agent = ureq::AgentBuilder::new().timeout(config.timeout).build();
+        // Previous counter value, for rates
+        let mut last: Option<(f64, Instant)> = None;
+
+        let worker_config = config.clone();
+        let poll = move || {
+            let scraped_at = Instant::now();
+            let body = agent
+                .get(&worker_config.url)
+                .call()
+                .map_err(|e| e.to_string())?
+                .into_string()
+                .map_err(|e| e.to_string())?;
+            let value = worker_config
+                .select(&body)
+                .ok_or_else(|| "no matching series".to_string())?;
+
+            if !worker_config.rate {
+                return Ok(Some(value));
+            }
+            let rate = last.map(|last| counter_rate(last, (value, scraped_at)));
+            last = Some((value, scraped_at));
+            Ok(rate)
+        };
+        let poller = Poller::spawn(
+            format!("prometheus '{}'", config.name),
+            config.interval,
+            poll,
+        );
+
+        Self {
+            metric_id: config.metric_id(),
+            config,
+            poller,
+            level: 0.0,
+        }
+    }
+
+    /// Returns the series' latest value (or rate), normalized between min and max
+    pub fn level(&self) -> MetricValue {
+        MetricValue::new(self.level)
+    }
+}
+
+impl MetricSource for PrometheusSource {
+    fn name(&self) -> &str {
+        &self.metric_id
+    }
+
+    fn refresh(&mut self) {
+        if let Some(reading) = self.poller.latest() {
+            self.level = scale(reading, self.config.min, self.config.max);
+        }
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        vec![Metric::new(&self.metric_id, self.level())]
+    }
+}
+
+/// Per-second increase of a counter between two scrapes
+fn counter_rate((last_value, last_time): (f64, Instant), (value, time): (f64, Instant)) -> f64 {
+    let elapsed = time.duration_since(last_time).as_secs_f64();
+    // A counter going down has been reset; skip that interval
+    if elapsed > 0.0 && value >= last_value {
+        (value - last_value) / elapsed
+    } else {
+        0.0
+    }
+}
+
+/// Parse a sample line of the text exposition format, e.g.
+/// `http_requests_total{method="post",code="200"} 1027 1395066363000`.
+/// Comments, `# HELP`/`# TYPE` lines and malformed lines yield None.
+fn parse_sample(line: &str) -> Option<(&str, Labels, f64)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let name_end = line.find(|c: char| c == '{' || c.is_whitespace())?;
+    let name = &line[..name_end];
+    let rest = &line[name_end..];
+
+    let (labels, rest) = if rest.starts_with('{') {
+        let (pairs, rest) = parse_labels(rest)?;
+        let labels = pairs
+            .into_iter()
+            .filter(|(_, op, _)| *op == "=")
+            .map(|(label, _, value)| (label, value))
+            .collect();
+        (labels, rest)
+    } else {
+        (Vec::new(), rest)
+    };
+
+    // The value is followed by an optional timestamp
+    let value = rest.split_whitespace().next()?.parse().ok()?;
+    Some((name, labels, value))
+}
+
+/// Parse `{label="value",other=~"regex"}` at the start of `input`, returning the
+/// label, operator and unescaped value of each pair, and the text after the braces
+fn parse_labels(input: &str) -> Option<(LabelPairs, &str)> {
+    let mut rest = input.strip_prefix('{')?;
+    let mut pairs = Vec::new();
+
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix('}') {
+            return Some((pairs, after));
+        }
+
+        let label_end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))?;
+        let label = rest[..label_end].to_string();
+        rest = rest[label_end..].trim_start();
+
+        let op = ["=~", "!~", "!=", "="]
+            .into_iter()
+            .find(|op| rest.starts_with(op))?;
+        rest = rest[op.len()..].trim_start().strip_prefix('"')?;
+
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let close = loop {
+            match chars.next()? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        rest = rest[close + 1..].trim_start();
+        pairs.push((label, op, value));
+
+        if let Some(after) = rest.strip_prefix(',') {
+            rest = after;
+        } else if !rest.starts_with('}') {
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPOSITION: &str = r#"# HELP http_requests_total The total number of HTTP requests.
+# TYPE http_requests_total counter
+http_requests_total{method="post",code="200"} 1027 1395066363000
+http_requests_total{method="post",code="500"} 3 1395066363000
+http_requests_total{method="get",code="503"} 5
+process_open_fds 42
+"#;
+
+    fn scrape(series: &str) -> ScrapeConfig {
+        ScrapeConfig::new(
+            "test",
+            "http://localhost:9100/metrics",
+            SeriesSelector::parse(series).unwrap(),
+        )
+    }
+
+    fn labels(pairs: &[(&str, &str)]) -> Labels {
+        pairs
+            .iter()
+            .map(|(l, v)| (l.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn samples_with_and_without_labels() {
+        assert_eq!(
+            parse_sample("process_open_fds 42"),
+            Some(("process_open_fds", Vec::new(), 42.0))
+        );
+        assert_eq!(
+            parse_sample(r#"http_requests_total{method="post",code="200"} 1027 1395066363000"#),
+            Some((
+                "http_requests_total",
+                labels(&[("method", "post"), ("code", "200")]),
+                1027.0
+            ))
+        );
+        assert_eq!(
+            parse_sample("temperature{} -1.5e1"),
+            Some(("temperature", Vec::new(), -15.0))
+        );
+    }
+
+    #[test]
+    fn escaped_quotes_and_commas_stay_in_label_values() {
+        let (_, labels, value) =
+            parse_sample(r#"build_info{version="1.0, \"beta\"",path="C:\\x"} 1"#).unwrap();
+        assert_eq!(
+            labels[0],
+            ("version".to_string(), r#"1.0, "beta""#.to_string())
+        );
+        assert_eq!(labels[1], ("path".to_string(), r"C:\x".to_string()));
+        assert_eq!(value, 1.0);
+    }
+
+    #[test]
+    fn comment_lines_are_not_samples() {
+        assert_eq!(
+            parse_sample("# HELP http_requests_total The total number of HTTP requests."),
+            None
+        );
+        assert_eq!(parse_sample("# TYPE http_requests_total counter"), None);
+        assert_eq!(parse_sample(""), None);
+        assert_eq!(
+            scrape("http_requests_total{method=\"get\"}").select(EXPOSITION),
+            Some(5.0)
+        );
+    }
+
+    #[test]
+    fn matching_series_are_added_up() {
+        assert_eq!(
+            scrape("http_requests_total").select(EXPOSITION),
+            Some(1035.0)
+        );
+        assert_eq!(
+            scrape(r#"http_requests_total{code=~"5.."}"#).select(EXPOSITION),
+            Some(8.0)
+        );
+        assert_eq!(
+            scrape(r#"http_requests_total{code!~"5..",method!="get"}"#).select(EXPOSITION),
+            Some(1027.0)
+        );
+        assert_eq!(
+            scrape(r#"http_requests_total{code="404"}"#).select(EXPOSITION),
+            None
+        );
+    }
+
+    #[test]
+    fn selector_regexes_match_whole_values() {
+        assert_eq!(
+            scrape(r#"http_requests_total{code=~"5"}"#).select(EXPOSITION),
+            None
+        );
+        assert!(SeriesSelector::parse(r#"up{job=~"("}"#).is_err());
+        assert!(SeriesSelector::parse(r#"{job="api"}"#).is_err());
+        assert!(SeriesSelector::parse(r#"up{job="api"} extra"#).is_err());
+    }
+
+    #[test]
+    fn counter_reset_gives_zero_rate() {
+        let start = Instant::now();
+        let later = start + Duration::from_secs(2);
+        assert_eq!(counter_rate((100.0, start), (110.0, later)), 5.0);
+        assert_eq!(counter_rate((100.0, start), (4.0, later)), 0.0);
+        assert_eq!(counter_rate((100.0, start), (120.0, start)), 0.0);
+    }
+}
@@ -0,0 +1,562 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{Metric, MetricSource, MetricValue};
+
+/// Where other programs can push metrics, and how long pushed values stay valid
+#[derive(Debug, Clone)]
+pub struct PushConfig {
+    /// Unix socket to listen on
+    pub socket: Option<PathBuf>,
+    /// Named pipe to read from
+    pub fifo: Option<PathBuf>,
+    /// Time a value is held after its last update
+    pub stale_after: Duration,
+    /// Time a stale value takes to fade to 0.0
+    pub decay: Duration,
+}
+
+impl PushConfig {
+    /// Default socket, `$XDG_RUNTIME_DIR/charm-linux/metrics.sock`
+    pub fn default_socket() -> Option<PathBuf> {
+        Self::runtime_dir().map(|dir| dir.join("metrics.sock"))
+    }
+
+    /// Default named pipe, `$XDG_RUNTIME_DIR/charm-linux/metrics.fifo`
+    pub fn default_fifo() -> Option<PathBuf> {
+        Self::runtime_dir().map(|dir| dir.join("metrics.fifo"))
+    }
+
+    fn runtime_dir() -> Option<PathBuf> {
+        directories::BaseDirs::new()
+            .and_then(|dirs| dirs.runtime_dir().map(|dir| dir.join("charm-linux")))
+    }
+
+    /// Whether any input endpoint is configured
+    pub fn is_enabled(&self) -> bool {
+        self.socket.is_some() || self.fifo.is_some()
+    }
+}
+
+impl Default for PushConfig {
+    fn default() -> Self {
+        Self {
+            socket: None,
+            fifo: None,
+            stale_after: Duration::from_secs(5),
+            decay: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Producers that can be connected to the socket at once; later ones are turned away
+const MAX_CONNECTIONS: usize = 16;
+
+/// Distinct names that can be held at once; values for further names are dropped
+/// until earlier ones fade out
+const MAX_METRICS: usize = 256;
+
+/// Longest message read, in bytes; longer lines are dropped
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// Latest value pushed for a metric
+struct Reading {
+    value: f64,
+    updated: Instant,
+}
+
+type Readings = Arc<Mutex<HashMap<String, Reading>>>;
+
+/// Publishes values pushed by other programs over a Unix socket or named pipe.
+///
+/// Each line is either `name value [min max]` or a JSON object, which is either
+/// `{"name": "tests.failed", "value": 3, "min": 0, "max": 20}` or a map of names to
+/// values. Without min and max, values are taken to be between 0.0 and 1.0.
+/// Values are published as `push.<name>`, and fade out once their producer goes quiet.
+pub struct PushSource {
+    config: PushConfig,
+    readings: Readings,
+    values: Vec<Metric>,
+    /// Socket this source created, removed again when it is dropped
+    bound_socket: Option<PathBuf>,
+    /// Named pipe this source created, removed again when it is dropped
+    created_fifo: Option<PathBuf>,
+}
+
+impl PushSource {
+    pub fn new(config: PushConfig) -> Self {
+        let readings = Readings::default();
+
+        let bound_socket =
+            config
+                .socket
+                .clone()
+                .filter(|path| match listen_socket(path, Arc::clone(&readings)) {
+                    Ok(()) => {
+                        println!("Listening for metrics on {}", path.display());
+                        true
+                    }
+                    Err(e) => {
+                        eprintln!("Warning: cannot listen on {}: {}", path.display(), e);
+                        false
+                    }
+                });
+
+        let created_fifo =
+            config
+                .fifo
+                .clone()
+                .filter(|path| match read_fifo(path, Arc::clone(&readings)) {
+                    Ok(created) => {
+                        println!("Reading metrics from {}", path.display());
+                        created
+                    }
+                    Err(e) => {
+                        eprintln!("Warning: cannot read from {}: {}", path.display(), e);
+                        false
+                    }
+                });
+
+        Self {
+            config,
+            readings,
+            values: Vec::new(),
+            bound_socket,
+            created_fifo,
+        }
+    }
+
+    /// Level of a reading after `age` without updates
+    fn decayed(&self, value: f64, age: Duration) -> f64 {
+        let Some(stale_for) = age.checked_sub(self.config.stale_after) else {
+            return value;
+        };
+        if self.config.decay.is_zero() {
+            return 0.0;
+        }
+        let remaining = 1.0 - stale_for.as_secs_f64() / self.config.decay.as_secs_f64();
+        value * remaining.max(0.0)
+    }
+}
+
+impl MetricSource for PushSource {
+    fn name(&self) -> &str {
+        "push"
+    }
+
+    fn refresh(&mut self) {
+        let now = Instant::now();
+        let Ok(mut readings) = self.readings.lock() else {
+            return;
+        };
+
+        let mut values = Vec::with_capacity(readings.len());
+        for (name, reading) in readings.iter() {
+            let level = self.decayed(reading.value, now.duration_since(reading.updated));
+            values.push(Metric::new(
+                &format!("push.{}", name),
+                MetricValue::new(level),
+            ));
+        }
+
+        // Forget metrics that have fully faded out
+        let forget_after = self.config.stale_after + self.config.decay;
+        readings.retain(|_, reading| now.duration_since(reading.updated) <= forget_after);
+
+        self.values = values;
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        self.values.clone()
+    }
+}
+
+impl Drop for PushSource {
+    fn drop(&mut self) {
+        for path in [&self.bound_socket, &self.created_fifo]
+            .into_iter()
+            .flatten()
+        {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Accept connections on a Unix socket, reading messages from each on its own thread
+fn listen_socket(path: &Path, readings: Readings) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    if path.exists() {
+        // A socket nobody answers on is left over from an earlier run
+        if UnixStream::connect(path).is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                "another instance is already listening",
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    thread::spawn(move || {
+        let connections = Arc::new(AtomicUsize::new(0));
+        for stream in listener.incoming().flatten() {
+            // Dropping the stream closes the connection
+            if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                connections.fetch_sub(1, Ordering::SeqCst);
+                continue;
+            }
+            let readings = Arc::clone(&readings);
+            let connections = Arc::clone(&connections);
+            thread::spawn(move || {
+                read_messages(stream, &readings);
+                connections.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// Read messages from a named pipe (created if missing), reopening it after each writer.
+/// Returns whether the pipe was created.
+fn read_fifo(path: &Path, readings: Readings) -> std::io::Result<bool> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let created = match fs::metadata(path) {
+        Ok(metadata) if metadata.file_type().is_fifo() => false,
+        Ok(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                "file exists and is not a named pipe",
+            ))
+        }
+        Err(_) => {
+            let c_path = CString::new(path.as_os_str().as_bytes())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+            // SAFETY: c_path is a valid NUL-terminated string for the duration of the call
+            if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            true
+        }
+    };
+
+    let path = path.to_path_buf();
+    thread::spawn(move || loop {
+        // Opening blocks until a writer appears; reading ends when the last writer closes
+        match File::open(&path) {
+            Ok(file) => read_messages(file, &readings),
+            Err(e) => {
+                eprintln!("Warning: cannot read from {}: {}", path.display(), e);
+                return;
+            }
+        }
+    });
+
+    Ok(created)
+}
+
+/// Store every value in a stream of messages until it ends
+fn read_messages(input: impl Read, readings: &Mutex<HashMap<String, Reading>>) {
+    let mut reader = BufReader::new(input);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        // One byte over the limit tells a line that is too long from one that just fits
+        let limit = MAX_LINE_BYTES as u64 + 1;
+        match reader.by_ref().take(limit).read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        if line.len() > MAX_LINE_BYTES && !line.ends_with(b"\n") {
+            if !skip_line(&mut reader) {
+                return;
+            }
+            continue;
+        }
+
+        let Ok(line) = std::str::from_utf8(&line) else {
+            continue;
+        };
+        let values = parse_message(line);
+        if values.is_empty() {
+            continue;
+        }
+
+        let Ok(mut readings) = readings.lock() else {
+            return;
+        };
+        let updated = Instant::now();
+        for (name, value) in values {
+            if readings.len() >= MAX_METRICS && !readings.contains_key(&name) {
+                continue;
+            }
+            readings.insert(name, Reading { value, updated });
+        }
+    }
+}
+
+/// Skip the rest of a line. Returns false once the input has ended.
+fn skip_line(reader: &mut impl BufRead) -> bool {
+    loop {
+        let (skipped, found) = match reader.fill_buf() {
+            Ok([]) | Err(_) => return false,
+            Ok(buf) => match buf.iter().position(|&b| b == b'\n') {
+                Some(end) => (end + 1, true),
+                None => (buf.len(), false),
+            },
+        };
+        reader.consume(skipped);
+        if found {
+            return true;
+        }
+    }
+}
+
+/// Parse one message into normalized values. Malformed messages yield nothing.
+fn parse_message(line: &str) -> Vec<(String, f64)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Vec::new();
+    }
+
+    let values = if line.starts_with('{') {
+        parse_json(line)
+    } else {
+        parse_line(line).into_iter().collect()
+    };
+
+    values
+        .into_iter()
+        .filter(|(name, value)| is_valid_name(name) && value.is_finite())
+        .collect()
+}
+
+/// `name value [min max]`
+fn parse_line(line: &str) -> Option<(String, f64)> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let numbers: Vec<f64> = fields
+        .get(1..)?
+        .iter()
+        .map(|f| f.parse().ok())
+        .collect::<Option<_>>()?;
+    if numbers.iter().any(|n| !n.is_finite()) {
+        return None;
+    }
+
+    let value = match numbers.as_slice() {
+        [value] => *value,
+        [value, min, max] => scale(*value, *min, *max),
+        _ => return None,
+    };
+
+    Some((fields[0].to_string(), value))
+}
+
+/// `{"name": ..., "value": ..., "min": ..., "max": ...}` or `{"name": value, ...}`
+fn parse_json(line: &str) -> Vec<(String, f64)> {
+    let Ok(Value::Object(object)) = serde_json::from_str::<Value>(line) else {
+        return Vec::new();
+    };
+
+    if let (Some(name), Some(value)) = (
+        object.get("name").and_then(Value::as_str),
+        object.get("value").and_then(Value::as_f64),
+    ) {
+        let value = match (
+            object.get("min").and_then(Value::as_f64),
+            object.get("max").and_then(Value::as_f64),
+        ) {
+            (Some(min), Some(max)) => scale(value, min, max),
+            _ => value,
+        };
+        return vec![(name.to_string(), value)];
+    }
+
+    object
+        .iter()
+        .filter_map(|(name, value)| value.as_f64().map(|v| (name.clone(), v)))
+        .collect()
+}
+
+/// Map a value onto 0.0-1.0 between min and max
+fn scale(value: f64, min: f64, max: f64) -> f64 {
+    if max == min {
+        return 0.0;
+    }
+    (value - min) / (max - min)
+}
+
+/// Names become part of a metric ID, so keep them to a safe character set
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(stale_after: u64, decay: u64) -> PushSource {
+        PushSource::new(PushConfig {
+            stale_after: Duration::from_secs(stale_after),
+            decay: Duration::from_secs(decay),
+            ..PushConfig::default()
+        })
+    }
+
+    fn parsed(line: &str) -> Vec<(String, f64)> {
+        let mut values = parse_message(line);
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        values
+    }
+
+    fn value(name: &str, value: f64) -> (String, f64) {
+        (name.to_string(), value)
+    }
+
+    #[test]
+    fn name_and_value() {
+        assert_eq!(parsed("tests.progress 0.4"), [value("tests.progress", 0.4)]);
+        assert_eq!(
+            parsed("  build-queue_2\t1  "),
+            [value("build-queue_2", 1.0)]
+        );
+    }
+
+    #[test]
+    fn name_value_and_range() {
+        assert_eq!(parsed("tests.failed 3 0 20"), [value("tests.failed", 0.15)]);
+        assert_eq!(parsed("free 5 10 0"), [value("free", 0.5)]);
+        assert_eq!(parsed("flat 3 2 2"), [value("flat", 0.0)]);
+        assert!(parsed("tests.failed 3 0").is_empty());
+        assert!(parsed("tests.failed three").is_empty());
+        assert!(parsed("tests.failed").is_empty());
+    }
+
+    #[test]
+    fn json_object_with_name_and_value() {
+        assert_eq!(
+            parsed(r#"{"name": "tests.failed", "value": 3, "min": 0, "max": 20}"#),
+            [value("tests.failed", 0.15)]
+        );
+        assert_eq!(
+            parsed(r#"{"name": "tests.progress", "value": 0.4}"#),
+            [value("tests.progress", 0.4)]
+        );
+    }
+
+    #[test]
+    fn json_map_of_names_to_values() {
+        assert_eq!(
+            parsed(r#"{"tests.progress": 0.4, "tests.running": 1, "label": "x"}"#),
+            [value("tests.progress", 0.4), value("tests.running", 1.0)]
+        );
+        assert!(parsed(r#"[1, 2]"#).is_empty());
+        assert!(parsed(r#"{"tests.progress": 0.4"#).is_empty());
+    }
+
+    #[test]
+    fn bad_names_and_non_finite_values_are_rejected() {
+        assert!(parsed("tests/progress 0.4").is_empty());
+        assert!(parsed("tests progress 0.4").is_empty());
+        assert!(parsed(r#"{"name": "a b", "value": 1}"#).is_empty());
+        assert_eq!(parsed(r#"{"ok": 1, "not ok": 1}"#), [value("ok", 1.0)]);
+        assert!(parsed("tests.progress NaN").is_empty());
+        assert!(parsed("tests.progress inf").is_empty());
+        assert!(parsed("tests.failed 3 0 1e309").is_empty());
+        assert!(parsed("# tests.progress 0.4").is_empty());
+    }
+
+    #[test]
+    fn values_hold_then_fade_out() {
+        let source = source(5, 2);
+        let secs = Duration::from_secs_f64;
+        assert_eq!(source.decayed(0.8, secs(0.0)), 0.8);
+        assert_eq!(source.decayed(0.8, secs(5.0)), 0.8);
+        assert!((source.decayed(0.8, secs(6.0)) - 0.4).abs() < 1e-9);
+        assert!((source.decayed(0.8, secs(6.5)) - 0.2).abs() < 1e-9);
+        assert_eq!(source.decayed(0.8, secs(7.0)), 0.0);
+        assert_eq!(source.decayed(0.8, secs(60.0)), 0.0);
+    }
+
+    #[test]
+    fn no_decay_time_drops_stale_values_at_once() {
+        let source = source(5, 0);
+        assert_eq!(source.decayed(0.8, Duration::from_secs_f64(4.9)), 0.8);
+        assert_eq!(source.decayed(0.8, Duration::from_secs_f64(5.1)), 0.0);
+    }
+
+    #[test]
+    fn new_names_beyond_the_limit_are_dropped() {
+        let readings = Mutex::new(HashMap::new());
+        let input: String = (0..=MAX_METRICS).map(|i| format!("m{} 1\n", i)).collect();
+        read_messages(input.as_bytes(), &readings);
+        read_messages("m0 0.5\n".as_bytes(), &readings);
+
+        let readings = readings.lock().unwrap();
+        assert_eq!(readings.len(), MAX_METRICS);
+        assert!(!readings.contains_key(&format!("m{}", MAX_METRICS)));
+        assert_eq!(readings["m0"].value, 0.5);
+    }
+
+    #[test]
+    fn oversized_lines_are_dropped() {
+        let readings = Mutex::new(HashMap::new());
+        let long = format!("long {}\n", "1".repeat(MAX_LINE_BYTES));
+        let fits = format!("fits {:<1$}\n", 1, MAX_LINE_BYTES - 5);
+        let input = format!("{}before 1\n{}{}after 1", long, long, fits);
+        read_messages(input.as_bytes(), &readings);
+
+        let readings = readings.lock().unwrap();
+        let mut names: Vec<&str> = readings.keys().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(names, ["after", "before", "fits"]);
+    }
+
+    #[test]
+    fn created_fifo_is_removed_when_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let fifo = dir.path().join("metrics.fifo");
+        let source = PushSource::new(PushConfig {
+            fifo: Some(fifo.clone()),
+            ..PushConfig::default()
+        });
+        assert!(fs::metadata(&fifo).unwrap().file_type().is_fifo());
+        drop(source);
+        assert!(!fifo.exists());
+    }
+
+    #[test]
+    fn existing_fifo_is_kept_when_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let fifo = dir.path().join("metrics.fifo");
+        let c_path = CString::new(fifo.as_os_str().as_bytes()).unwrap();
+        // SAFETY: c_path is a valid NUL-terminated string for the duration of the call
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+
+        drop(PushSource::new(PushConfig {
+            fifo: Some(fifo.clone()),
+            ..PushConfig::default()
+        }));
+        assert!(fifo.exists());
+    }
+}
@@ -0,0 +1,228 @@
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use super::{Metric, MetricValue, PowerMonitor, SystemMetrics};
+
+/// Writes timestamped metric snapshots to a JSON Lines file.
+///
+/// Each line holds the seconds since recording started and every metric, e.g.
+/// `{"t":1.2,"metrics":{"memory.usage":0.41,"cpu.core":[["0",0.2],["1",0.7]]}}`.
+/// Metrics with a single unlabelled reading are written as a plain number.
+pub struct MetricRecorder {
+    writer: BufWriter<File>,
+    start: Instant,
+}
+
+impl MetricRecorder {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            start: Instant::now(),
+        })
+    }
+
+    /// Append a snapshot, timestamped with the time since recording started
+    pub fn record(&mut self, metrics: &SystemMetrics) -> std::io::Result<()> {
+        let mut values = Map::new();
+        for (id, instances) in metrics.iter() {
+            let value = match instances {
+                [(None, value)] => json!(value.get()),
+                _ => Value::Array(
+                    instances
+                        .iter()
+                        .map(|(label, value)| json!([label, value.get()]))
+                        .collect(),
+                ),
+            };
+            values.insert(id.to_string(), value);
+        }
+
+        let line = json!({
+            "t": self.start.elapsed().as_secs_f64(),
+            "metrics": values,
+        });
+        writeln!(self.writer, "{}", line)?;
+        // Keep the file usable if the recording is cut short
+        self.writer.flush()
+    }
+}
+
+/// Plays back snapshots written by `MetricRecorder`, looping at the end
+pub struct MetricReplay {
+    /// Snapshots with their time in seconds, in order
+    snapshots: Vec<(f64, SystemMetrics)>,
+    speed: f64,
+    start: Instant,
+    /// Playback time of the latest `current` snapshot
+    last_elapsed: Option<f64>,
+}
+
+impl MetricReplay {
+    /// Load a recording, played back `speed` times faster than it was recorded
+    pub fn load(path: &Path, speed: f64) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let mut snapshots = Vec::new();
+
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let snapshot = parse_snapshot(&line)
+                .ok_or_else(|| format!("line {} is not a snapshot", number + 1))?;
+            snapshots.push(snapshot);
+        }
+
+        if snapshots.is_empty() {
+            return Err("recording is empty".to_string());
+        }
+
+        Ok(Self {
+            snapshots,
+            speed,
+            start: Instant::now(),
+            last_elapsed: None,
+        })
+    }
+
+    /// Returns the snapshot for the current playback time
+    pub fn current(&mut self) -> SystemMetrics {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let metrics = self.at(elapsed, self.last_elapsed);
+        self.last_elapsed = Some(elapsed);
+        metrics
+    }
+
+    /// Returns the snapshot `elapsed` seconds into playback (at the replay speed).
+    /// Events fired by snapshots skipped since `previous` seconds, as happens at speeds
+    /// above 1, are carried into it so they are still heard.
+    pub fn at(&self, elapsed: f64, previous: Option<f64>) -> SystemMetrics {
+        let index = self.index_at(elapsed);
+        let mut metrics = self.snapshots[index].1.clone();
+
+        let Some(previous) = previous.map(|p| self.index_at(p)) else {
+            return metrics;
+        };
+        if previous == index {
+            return metrics;
+        }
+        // Wrapping around when the recording has started over
+        let skipped = (previous + 1..)
+            .map(|i| i % self.snapshots.len())
+            .take_while(|&i| i != index);
+        for i in skipped {
+            let snapshot = &self.snapshots[i].1;
+            for event in PowerMonitor::EVENTS {
+                if snapshot.get(event).get() >= 1.0 {
+                    metrics.insert(vec![Metric::new(event, MetricValue::new(1.0))]);
+                }
+            }
+        }
+        metrics
+    }
+
+    /// Index of the snapshot `elapsed` seconds into playback
+    fn index_at(&self, elapsed: f64) -> usize {
+        // Start over from the beginning of the recording once it has played through
+        let time = (elapsed * self.speed).rem_euclid(self.recorded_duration());
+        let index = self.snapshots.partition_point(|(t, _)| *t <= time);
+        index.saturating_sub(1)
+    }
+
+    /// Returns how long one pass through the recording plays for, in seconds
+    pub fn duration(&self) -> f64 {
+        self.recorded_duration() / self.speed
+    }
+
+    /// Length of the recording in recorded seconds
+    fn recorded_duration(&self) -> f64 {
+        // The last snapshot lasts as long as the average one before starting over
+        let last = self.snapshots.last().map(|(t, _)| *t).unwrap_or(0.0);
+        let spacing = last / self.snapshots.len().saturating_sub(1).max(1) as f64;
+        // A recording of a single snapshot (or one instant) still needs some length
+        (last + spacing).max(f64::EPSILON)
+    }
+}
+
+/// Parse one line of a recording
+fn parse_snapshot(line: &str) -> Option<(f64, SystemMetrics)> {
+    let value: Value = serde_json::from_str(line).ok()?;
+    let time = value.get("t")?.as_f64()?;
+
+    let mut metrics = SystemMetrics::default();
+    for (id, reading) in value.get("metrics")?.as_object()? {
+        let readings = match reading {
+            Value::Array(instances) => instances
+                .iter()
+                .filter_map(|instance| {
+                    let label = instance.get(0)?.as_str();
+                    let value = MetricValue::new(instance.get(1)?.as_f64()?);
+                    Some(match label {
+                        Some(label) => Metric::instance(id, label, value),
+                        None => Metric::new(id, value),
+                    })
+                })
+                .collect(),
+            _ => vec![Metric::new(id, MetricValue::new(reading.as_f64()?))],
+        };
+        metrics.insert(readings);
+    }
+
+    Some((time, metrics))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A replay of one snapshot per second, with power connected in the second one
+    fn replay(speed: f64) -> (tempfile::NamedTempFile, MetricReplay) {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for (t, connected) in [(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (3.0, 0.0)] {
+            writeln!(
+                file,
+                r#"{{"t":{},"metrics":{{"memory.usage":{},"power.connected":{}}}}}"#,
+                t,
+                t / 10.0,
+                connected
+            )
+            .unwrap();
+        }
+        let replay = MetricReplay::load(file.path(), speed).unwrap();
+        (file, replay)
+    }
+
+    #[test]
+    fn plays_back_the_snapshot_at_a_time() {
+        let (_file, replay) = replay(1.0);
+        assert_eq!(replay.at(2.5, None).get("memory.usage").get(), 0.2);
+        // Starts over after the last snapshot's share of the recording
+        assert_eq!(replay.at(4.5, None).get("memory.usage").get(), 0.0);
+        assert_eq!(replay.duration(), 4.0);
+    }
+
+    #[test]
+    fn carries_events_from_skipped_snapshots() {
+        let (_file, replay) = replay(4.0);
+        // 0.25s at 4x jumps from the first snapshot past the second, to the third
+        let metrics = replay.at(0.5, Some(0.0));
+        assert_eq!(metrics.get("memory.usage").get(), 0.2);
+        assert_eq!(metrics.get("power.connected").get(), 1.0);
+
+        // The event only fires once
+        let metrics = replay.at(0.75, Some(0.5));
+        assert_eq!(metrics.get("power.connected").get(), 0.0);
+    }
+
+    #[test]
+    fn carries_events_across_the_loop() {
+        let (_file, replay) = replay(4.0);
+        // From the last snapshot round to the third, skipping the first two
+        let metrics = replay.at(1.5, Some(0.75));
+        assert_eq!(metrics.get("memory.usage").get(), 0.2);
+        assert_eq!(metrics.get("power.connected").get(), 1.0);
+    }
+}
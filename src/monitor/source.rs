@@ -0,0 +1,97 @@
+use std::any::Any;
+
+use super::{MetricValue, SystemMetrics};
+
+/// One reading published by a metric source
+#[derive(Debug, Clone)]
+pub struct Metric {
+    /// Metric ID that pack channels bind to, e.g. "memory.usage" or "cpu.core"
+    pub id: String,
+    /// Instance label for metrics with several values (a core number, a device name...)
+    pub label: Option<String>,
+    pub value: MetricValue,
+}
+
+impl Metric {
+    pub fn new(id: &str, value: MetricValue) -> Self {
+        Self {
+            id: id.to_string(),
+            label: None,
+            value,
+        }
+    }
+
+    /// A reading for one instance of a metric with several values
+    pub fn instance(id: &str, label: impl Into<String>, value: MetricValue) -> Self {
+        Self {
+            id: id.to_string(),
+            label: Some(label.into()),
+            value,
+        }
+    }
+}
+
+/// Something that produces metrics for the audio engine.
+///
+/// Events (like a charger being plugged in) are published as a metric that reads 1.0
+/// for the single refresh in which the event happened, and 0.0 otherwise.
+pub trait MetricSource: Any {
+    /// Unique name of the source, used as its registry key
+    fn name(&self) -> &str;
+
+    /// Take a new measurement
+    fn refresh(&mut self);
+
+    /// Readings from the latest refresh
+    fn values(&self) -> Vec<Metric>;
+}
+
+/// The set of metric sources, keyed by name and refreshed in registration order.
+/// When two sources publish the same metric ID, the later one wins.
+#[derive(Default)]
+pub struct MetricRegistry {
+    sources: Vec<Box<dyn MetricSource>>,
+}
+
+impl MetricRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a source, replacing any source registered under the same name
+    pub fn register(&mut self, source: Box<dyn MetricSource>) {
+        match self.sources.iter().position(|s| s.name() == source.name()) {
+            Some(index) => self.sources[index] = source,
+            None => self.sources.push(source),
+        }
+    }
+
+    /// Remove the source registered under a name, if any
+    pub fn unregister(&mut self, name: &str) {
+        self.sources.retain(|s| s.name() != name);
+    }
+
+    /// Returns the first registered source of type `T`
+    pub fn get<T: MetricSource>(&self) -> Option<&T> {
+        self.sources
+            .iter()
+            .find_map(|s| (s.as_ref() as &dyn Any).downcast_ref::<T>())
+    }
+
+    /// Returns the first registered source of type `T`, mutably
+    pub fn get_mut<T: MetricSource>(&mut self) -> Option<&mut T> {
+        self.sources
+            .iter_mut()
+            .find_map(|s| (s.as_mut() as &mut dyn Any).downcast_mut::<T>())
+    }
+
+    /// Refresh every source and collect their readings
+    pub fn refresh(&mut self) -> SystemMetrics {
+        let mut metrics = SystemMetrics::default();
+        for source in &mut self.sources {
+            source.refresh();
+            metrics.insert(source.values());
+        }
+        metrics
+    }
+}
@@ -0,0 +1,296 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{Metric, MetricSource, MetricValue};
+
+/// Which sensor to follow and how its readings map to 0.0-1.0
+#[derive(Debug, Clone)]
+pub struct TemperatureConfig {
+    /// Sensor to follow, matched against its label, `chip/label` or `hwmonN/tempM`.
+    /// When unset, the hottest sensor is used.
+    pub sensor: Option<String>,
+    /// Temperature (Celsius) that maps to 0.0
+    pub idle_celsius: f64,
+    /// Temperature (Celsius) that maps to 1.0. When unset, the sensor's own
+    /// critical threshold is used, falling back to `DEFAULT_CRITICAL_CELSIUS`.
+    pub critical_celsius: Option<f64>,
+}
+
+impl TemperatureConfig {
+    pub const DEFAULT_CRITICAL_CELSIUS: f64 = 95.0;
+}
+
+impl Default for TemperatureConfig {
+    fn default() -> Self {
+        Self {
+            sensor: None,
+            idle_celsius: 40.0,
+            critical_celsius: None,
+        }
+    }
+}
+
+/// A temperature input exposed by a hwmon chip
+#[derive(Debug, Clone)]
+pub struct TemperatureSensor {
+    /// Chip name from the hwmon `name` file (e.g. "coretemp", "k10temp")
+    pub chip: String,
+    /// Sensor label (e.g. "Package id 0"), or the input name when unlabelled
+    pub label: String,
+    /// Stable identifier of the form `hwmonN/tempM`
+    pub id: String,
+    /// Critical threshold in Celsius, if the chip reports one
+    pub critical_celsius: Option<f64>,
+    input_path: PathBuf,
+}
+
+impl TemperatureSensor {
+    /// Check if a user-supplied sensor name refers to this sensor
+    fn matches(&self, name: &str) -> bool {
+        name.eq_ignore_ascii_case(&self.label)
+            || name.eq_ignore_ascii_case(&self.id)
+            || name.eq_ignore_ascii_case(&format!("{}/{}", self.chip, self.label))
+    }
+
+    /// Read the current temperature in Celsius
+    pub fn read_celsius(&self) -> Option<f64> {
+        read_millidegrees(&self.input_path)
+    }
+}
+
+/// Monitors hardware temperatures by reading /sys/class/hwmon
+pub struct TemperatureMonitor {
+    config: TemperatureConfig,
+    sensors: Vec<TemperatureSensor>,
+    /// Latest reading of the followed sensor, with the range it is normalized against
+    current: Option<(f64, f64)>,
+}
+
+impl TemperatureMonitor {
+    const HWMON_ROOT: &'static str = "/sys/class/hwmon";
+
+    pub fn new(config: TemperatureConfig) -> Self {
+        Self::with_root(Self::HWMON_ROOT, config)
+    }
+
+    /// Create a monitor that enumerates sensors below `root` instead of /sys/class/hwmon
+    pub fn with_root(root: impl AsRef<Path>, config: TemperatureConfig) -> Self {
+        let sensors = Self::enumerate_sensors(root.as_ref());
+
+        if let Some(ref name) = config.sensor {
+            if !sensors.iter().any(|s| s.matches(name)) {
+                eprintln!(
+                    "Warning: Temperature sensor '{}' not found, using the hottest sensor",
+                    name
+                );
+            }
+        }
+
+        Self {
+            config,
+            sensors,
+            current: None,
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        let followed = self
+            .config
+            .sensor
+            .as_deref()
+            .and_then(|name| self.sensors.iter().find(|s| s.matches(name)));
+
+        let reading = match followed {
+            Some(sensor) => sensor.read_celsius().map(|t| (sensor, t)),
+            None => self
+                .sensors
+                .iter()
+                .filter_map(|s| s.read_celsius().map(|t| (s, t)))
+                .max_by(|a, b| a.1.total_cmp(&b.1)),
+        };
+
+        self.current = reading.map(|(sensor, celsius)| {
+            let critical = self
+                .config
+                .critical_celsius
+                .or(sensor.critical_celsius)
+                .unwrap_or(TemperatureConfig::DEFAULT_CRITICAL_CELSIUS);
+            (celsius, critical)
+        });
+    }
+
+    /// Returns the followed temperature mapped from the idle-critical range to 0.0-1.0
+    pub fn level(&self) -> MetricValue {
+        match self.current {
+            Some((celsius, critical)) => {
+                let range = (critical - self.config.idle_celsius).max(1.0);
+                MetricValue::new((celsius - self.config.idle_celsius) / range)
+            }
+            None => MetricValue::new(0.0),
+        }
+    }
+
+    /// Returns all discovered sensors
+    pub fn sensors(&self) -> &[TemperatureSensor] {
+        &self.sensors
+    }
+
+    /// Find every `temp*_input` below `root`, with its label and critical threshold
+    fn enumerate_sensors(root: &Path) -> Vec<TemperatureSensor> {
+        let mut sensors = Vec::new();
+
+        let Ok(chips) = fs::read_dir(root) else {
+            return sensors;
+        };
+
+        let mut chip_dirs: Vec<PathBuf> = chips.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        chip_dirs.sort();
+
+        for chip_dir in chip_dirs {
+            let hwmon_name = file_name(&chip_dir);
+            let chip = fs::read_to_string(chip_dir.join("name"))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_else(|_| hwmon_name.clone());
+
+            let Ok(entries) = fs::read_dir(&chip_dir) else {
+                continue;
+            };
+
+            let mut inputs: Vec<String> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| file_name(&e.path()))
+                .filter(|name| name.starts_with("temp") && name.ends_with("_input"))
+                .collect();
+            inputs.sort();
+
+            for input in inputs {
+                // "temp1_input" -> "temp1"
+                let base = input.trim_end_matches("_input");
+
+                let label = fs::read_to_string(chip_dir.join(format!("{}_label", base)))
+                    .map(|s| s.trim().to_string())
+                    .unwrap_or_else(|_| base.to_string());
+
+                sensors.push(TemperatureSensor {
+                    chip: chip.clone(),
+                    label,
+                    id: format!("{}/{}", hwmon_name, base),
+                    critical_celsius: read_millidegrees(&chip_dir.join(format!("{}_crit", base))),
+                    input_path: chip_dir.join(&input),
+                });
+            }
+        }
+
+        sensors
+    }
+}
+
+impl Default for TemperatureMonitor {
+    fn default() -> Self {
+        Self::new(TemperatureConfig::default())
+    }
+}
+
+impl MetricSource for TemperatureMonitor {
+    fn name(&self) -> &str {
+        "temperature"
+    }
+
+    fn refresh(&mut self) {
+        TemperatureMonitor::refresh(self);
+    }
+
+    fn values(&self) -> Vec<Metric> {
+        vec![Metric::new("temperature.level", self.level())]
+    }
+}
+
+/// Read a hwmon value in millidegrees Celsius and convert it to Celsius
+fn read_millidegrees(path: &Path) -> Option<f64> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| s.trim().parse::<f64>().ok())
+        .map(|m| m / 1000.0)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::write_files;
+
+    fn two_chips() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        write_files(
+            root.path(),
+            &[
+                ("hwmon0/name", "coretemp\n"),
+                ("hwmon0/temp1_input", "60000\n"),
+                ("hwmon0/temp1_label", "Package id 0\n"),
+                ("hwmon0/temp1_crit", "100000\n"),
+                ("hwmon1/name", "nvme\n"),
+                ("hwmon1/temp1_input", "72500\n"),
+            ],
+        );
+        root
+    }
+
+    #[test]
+    fn enumerates_labelled_and_unlabelled_sensors() {
+        let root = two_chips();
+        let monitor = TemperatureMonitor::with_root(root.path(), TemperatureConfig::default());
+        let sensors = monitor.sensors();
+
+        assert_eq!(sensors.len(), 2);
+        assert_eq!(sensors[0].chip, "coretemp");
+        assert_eq!(sensors[0].label, "Package id 0");
+        assert_eq!(sensors[0].id, "hwmon0/temp1");
+        assert_eq!(sensors[0].critical_celsius, Some(100.0));
+        assert_eq!(sensors[1].label, "temp1");
+        assert_eq!(sensors[1].critical_celsius, None);
+        assert_eq!(sensors[1].read_celsius(), Some(72.5));
+    }
+
+    #[test]
+    fn follows_the_hottest_sensor_by_default() {
+        let root = two_chips();
+        let mut monitor = TemperatureMonitor::with_root(root.path(), TemperatureConfig::default());
+        monitor.refresh();
+
+        // 72.5°C between 40°C and the default critical 95°C
+        let expected = (72.5 - 40.0) / (95.0 - 40.0);
+        assert!((monitor.level().get() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn follows_a_named_sensor_against_its_critical_threshold() {
+        let root = two_chips();
+        let config = TemperatureConfig {
+            sensor: Some("coretemp/package id 0".to_string()),
+            ..TemperatureConfig::default()
+        };
+        let mut monitor = TemperatureMonitor::with_root(root.path(), config);
+        monitor.refresh();
+
+        assert!((monitor.level().get() - 20.0 / 60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn missing_hwmon_reads_zero() {
+        let root = tempfile::tempdir().unwrap();
+        let mut monitor = TemperatureMonitor::with_root(
+            root.path().join("missing"),
+            TemperatureConfig::default(),
+        );
+        monitor.refresh();
+
+        assert!(monitor.sensors().is_empty());
+        assert_eq!(monitor.level().get(), 0.0);
+    }
+}
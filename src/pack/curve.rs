@@ -0,0 +1,371 @@
+/// Shape of a channel's response between its floor and ceiling
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ResponseCurve {
+    /// Level follows the value
+    #[default]
+    Linear,
+    /// Rises quickly at first, so low values are already audible
+    Logarithmic,
+    /// `value ^ gamma`: above 1.0 holds low values back, below 1.0 lifts them
+    Gamma(f64),
+    /// Quiet at both ends and steep in the middle; higher steepness is steeper
+    SCurve(f64),
+    /// Straight lines between (value, level) points, sorted by value
+    Piecewise(Vec<(f64, f64)>),
+}
+
+impl ResponseCurve {
+    /// Default exponent of `exp`/`gamma`
+    const DEFAULT_GAMMA: f64 = 2.0;
+    /// Default steepness of `scurve`
+    const DEFAULT_STEEPNESS: f64 = 2.0;
+
+    /// Parse a curve: `linear`, `log`, `exp`/`gamma [exponent]`, `scurve [steepness]`,
+    /// or a list of `value:level` points such as `0:0, 0.3:0.6, 1:1`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if value.contains(':') {
+            return Self::parse_points(value);
+        }
+
+        let mut words = value.split_whitespace();
+        let name = words.next().unwrap_or("").to_ascii_lowercase();
+        let parameter = match words.next() {
+            Some(word) => Some(
+                word.parse::<f64>()
+                    .ok()
+                    .filter(|p| *p > 0.0 && p.is_finite())
+                    .ok_or_else(|| format!("invalid parameter '{}'", word))?,
+            ),
+            None => None,
+        };
+        if let Some(extra) = words.next() {
+            return Err(format!("unexpected '{}'", extra));
+        }
+
+        match name.as_str() {
+            "linear" => Ok(Self::Linear),
+            "log" | "logarithmic" => Ok(Self::Logarithmic),
+            "exp" | "exponential" | "gamma" => {
+                Ok(Self::Gamma(parameter.unwrap_or(Self::DEFAULT_GAMMA)))
+            }
+            "s" | "scurve" | "s-curve" => {
+                Ok(Self::SCurve(parameter.unwrap_or(Self::DEFAULT_STEEPNESS)))
+            }
+            _ => Err(format!("unknown curve '{}'", value)),
+        }
+    }
+
+    fn parse_points(value: &str) -> Result<Self, String> {
+        let mut points = Vec::new();
+        for point in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let parsed = point.split_once(':').and_then(|(input, level)| {
+                let input = input.trim().parse::<f64>().ok()?;
+                let level = level.trim().parse::<f64>().ok()?;
+                (input.is_finite() && level.is_finite()).then_some((input, level))
+            });
+            points.push(parsed.ok_or_else(|| format!("invalid point '{}'", point))?);
+        }
+
+        if points.is_empty() {
+            return Err("no points".to_string());
+        }
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Self::Piecewise(points))
+    }
+
+    /// Map a value between 0.0 and 1.0 onto a level between 0.0 and 1.0
+    pub fn apply(&self, value: f64) -> f64 {
+        let x = value.clamp(0.0, 1.0);
+        let level = match self {
+            Self::Linear => x,
+            Self::Logarithmic => (1.0 + 9.0 * x).log10(),
+            Self::Gamma(gamma) => x.powf(*gamma),
+            Self::SCurve(steepness) => {
+                let rising = x.powf(*steepness);
+                let falling = (1.0 - x).powf(*steepness);
+                if rising + falling == 0.0 {
+                    x
+                } else {
+                    rising / (rising + falling)
+                }
+            }
+            Self::Piecewise(points) => interpolate(points, x),
+        };
+        level.clamp(0.0, 1.0)
+    }
+}
+
+/// Level at `x` on the lines between `points`, flat beyond the first and last point
+fn interpolate(points: &[(f64, f64)], x: f64) -> f64 {
+    let Some(&(first_x, first_level)) = points.first() else {
+        return x;
+    };
+    if x <= first_x {
+        return first_level;
+    }
+
+    for pair in points.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        if x <= x1 {
+            if x1 == x0 {
+                return y1;
+            }
+            return y0 + (y1 - y0) * (x - x0) / (x1 - x0);
+        }
+    }
+
+    points.last().map(|&(_, level)| level).unwrap_or(x)
+}
+
+/// How a channel turns its metric into a level: the range from floor to ceiling is
+/// stretched over the full range, then shaped by the curve
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    /// Value at or below which the channel is idle
+    pub floor: f64,
+    /// Value at or above which the channel is at full level
+    pub ceiling: f64,
+    pub curve: ResponseCurve,
+}
+
+impl Response {
+    /// Map a metric value onto the channel's level, both between 0.0 and 1.0
+    pub fn apply(&self, value: f64) -> f64 {
+        let range = self.ceiling - self.floor;
+        let stretched = if range > 0.0 {
+            (value - self.floor) / range
+        } else if value >= self.ceiling {
+            1.0
+        } else {
+            0.0
+        };
+        self.curve.apply(stretched)
+    }
+}
+
+impl Default for Response {
+    fn default() -> Self {
+        Self {
+            floor: 0.0,
+            ceiling: 1.0,
+            curve: ResponseCurve::Linear,
+        }
+    }
+}
+
+/// Changes to a channel's response, read from its `<Name>Floor`, `<Name>Ceiling` and
+/// `<Name>Curve` keys (in a pack's prefs.ini, or the user's [response] section)
+#[derive(Debug, Clone, Default)]
+pub struct ResponseOverride {
+    /// Channel name as used in the keys, e.g. "RAM"
+    pub channel: String,
+    pub floor: Option<f64>,
+    pub ceiling: Option<f64>,
+    pub curve: Option<ResponseCurve>,
+}
+
+impl ResponseOverride {
+    /// Read a channel's keys from a section, warning about values that don't parse
+    pub fn read(section: &ini::Properties, channel: &str) -> Self {
+        let level = |key: &str| {
+            let value = section.get(format!("{}{}", channel, key))?;
+            let level = parse_level(value);
+            if level.is_none() {
+                eprintln!("Warning: Invalid {}{} '{}'", channel, key, value);
+            }
+            level
+        };
+
+        let curve = section
+            .get(format!("{}Curve", channel))
+            .and_then(|value| match ResponseCurve::parse(value) {
+                Ok(curve) => Some(curve),
+                Err(e) => {
+                    eprintln!("Warning: Invalid {}Curve: {}", channel, e);
+                    None
+                }
+            });
+
+        Self {
+            channel: channel.to_string(),
+            floor: level("Floor"),
+            ceiling: level("Ceiling"),
+            curve,
+        }
+    }
+
+    /// Read every channel mentioned in a section
+    pub fn read_all(section: &ini::Properties) -> Vec<Self> {
+        let mut channels: Vec<&str> = Vec::new();
+        for (key, _) in section.iter() {
+            let channel = ["Floor", "Ceiling", "Curve"]
+                .iter()
+                .find_map(|suffix| key.strip_suffix(suffix));
+            match channel {
+                Some(channel) if !channel.is_empty() => {
+                    if !channels.contains(&channel) {
+                        channels.push(channel);
+                    }
+                }
+                _ => eprintln!("Warning: Unknown response setting '{}'", key),
+            }
+        }
+
+        channels.into_iter().map(|channel| Self::read(section, channel)).collect()
+    }
+
+    /// Apply the changes that were given
+    pub fn apply(&self, response: &mut Response) {
+        if let Some(floor) = self.floor {
+            response.floor = floor;
+        }
+        if let Some(ceiling) = self.ceiling {
+            response.ceiling = ceiling;
+        }
+        if let Some(ref curve) = self.curve {
+            response.curve = curve.clone();
+        }
+    }
+}
+
+/// How a channel's value maps onto pitch when the pack enables FrequencyFluctuation:
+/// the curve is applied first, then the range is covered evenly in semitones
+#[derive(Debug, Clone, PartialEq)]
+pub struct PitchResponse {
+    /// Pitch ratio at the lowest value (1.0 = unchanged)
+    pub min: f64,
+    /// Pitch ratio at the highest value
+    pub max: f64,
+    pub curve: ResponseCurve,
+    /// Whether the range was given in semitones, so the curve moves evenly in
+    /// semitones rather than in ratio
+    pub semitones: bool,
+}
+
+impl PitchResponse {
+    /// Map a value between 0.0 and 1.0 onto a pitch ratio
+    pub fn apply(&self, value: f64) -> f64 {
+        let position = self.curve.apply(value);
+        if self.semitones {
+            self.min * (self.max / self.min).powf(position)
+        } else {
+            self.min + (self.max - self.min) * position
+        }
+    }
+
+    /// Read the pack-wide `PitchMin`, `PitchMax` and `PitchCurve` keys, then the
+    /// channel's own `<Name>PitchMin`, `<Name>PitchMax` and `<Name>PitchCurve`
+    pub fn read(section: &ini::Properties, channel: &str) -> Self {
+        let mut pitch = Self::default();
+        pitch.read_keys(section, "");
+        if !channel.is_empty() {
+            pitch.read_keys(section, channel);
+        }
+        pitch
+    }
+
+    fn read_keys(&mut self, section: &ini::Properties, prefix: &str) {
+        let ratio = |key: &str| {
+            let value = section.get(format!("{}{}", prefix, key))?;
+            let pitch = parse_pitch(value);
+            if pitch.is_none() {
+                eprintln!("Warning: Invalid {}{} '{}'", prefix, key, value);
+            }
+            pitch
+        };
+        if let Some((min, semitones)) = ratio("PitchMin") {
+            self.min = min;
+            self.semitones |= semitones;
+        }
+        if let Some((max, semitones)) = ratio("PitchMax") {
+            self.max = max;
+            self.semitones |= semitones;
+        }
+
+        if let Some(value) = section.get(format!("{}PitchCurve", prefix)) {
+            match ResponseCurve::parse(value) {
+                Ok(curve) => self.curve = curve,
+                Err(e) => eprintln!("Warning: Invalid {}PitchCurve: {}", prefix, e),
+            }
+        }
+    }
+}
+
+impl Default for PitchResponse {
+    fn default() -> Self {
+        Self {
+            min: 0.8,
+            max: 1.2,
+            curve: ResponseCurve::Linear,
+            semitones: false,
+        }
+    }
+}
+
+/// Parse a pitch as a ratio (0.8) or in semitones (-2st, +12 semitones).
+/// Returns the ratio and whether it was given in semitones.
+fn parse_pitch(value: &str) -> Option<(f64, bool)> {
+    let value = value.trim().to_ascii_lowercase();
+    let semitones = ["semitones", "semitone", "st"]
+        .iter()
+        .find_map(|suffix| value.strip_suffix(suffix));
+    let ratio = match semitones {
+        Some(semitones) => 2f64.powf(semitones.trim().parse::<f64>().ok()? / 12.0),
+        None => value.parse::<f64>().ok()?,
+    };
+    (ratio > 0.0 && ratio.is_finite()).then_some((ratio, semitones.is_some()))
+}
+
+/// Parse a level as a fraction (0.4) or a percentage (40%)
+fn parse_level(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let level = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().ok()? / 100.0,
+        None => value.parse::<f64>().ok()?,
+    };
+    level.is_finite().then_some(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pitch(prefs: &str, channel: &str) -> PitchResponse {
+        let ini = ini::Ini::load_from_str(prefs).unwrap();
+        PitchResponse::read(ini.section(Some("soundpack")).unwrap(), channel)
+    }
+
+    #[test]
+    fn default_pitch_is_linear_in_ratio() {
+        let pitch = PitchResponse::default();
+        assert_eq!(pitch.apply(0.0), 0.8);
+        assert!((pitch.apply(0.5) - 1.0).abs() < 1e-9);
+        assert_eq!(pitch.apply(1.0), 1.2);
+    }
+
+    #[test]
+    fn ratio_keys_stay_linear() {
+        let pitch = pitch("[soundpack]\nPitchMin=0.5\nPitchMax=1.5\n", "CPU");
+        assert!(!pitch.semitones);
+        assert!((pitch.apply(0.5) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn semitone_keys_move_evenly_in_semitones() {
+        let pitch = pitch("[soundpack]\nCPUPitchMin=-12st\nCPUPitchMax=+12 semitones\n", "CPU");
+        assert!(pitch.semitones);
+        assert!((pitch.apply(0.0) - 0.5).abs() < 1e-9);
+        assert!((pitch.apply(0.5) - 1.0).abs() < 1e-9);
+        assert!((pitch.apply(0.75) - 2f64.sqrt()).abs() < 1e-9);
+        assert!((pitch.apply(1.0) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn channel_keys_override_pack_keys() {
+        let prefs = "[soundpack]\nPitchMax=1.1\nRAMPitchMax=1.4\n";
+        assert_eq!(pitch(prefs, "CPU").max, 1.1);
+        assert_eq!(pitch(prefs, "RAM").max, 1.4);
+        assert_eq!(pitch(prefs, "RAM").min, 0.8);
+    }
+}
@@ -1,6 +1,11 @@
 use ini::Ini;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::curve::{PitchResponse, Response, ResponseOverride};
+use crate::config::strip_comment;
+use crate::monitor::{LoadSource, PressureStat, ProcessSource};
 
 /// Sound mode for a channel (matches Windows CHARM)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -25,6 +30,69 @@ impl SoundMode {
     }
 }
 
+/// What drives pitch when frequency fluctuation is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PitchSource {
+    /// Pitch follows the channel's metric (CPU load for CPU channels)
+    #[default]
+    Load,
+    /// Per-core CPU pitch follows each core's clock frequency
+    Frequency,
+}
+
+impl PitchSource {
+    pub fn from_name(value: &str) -> Self {
+        if value.trim().eq_ignore_ascii_case("frequency") {
+            Self::Frequency
+        } else {
+            Self::Load
+        }
+    }
+}
+
+/// How quickly a channel follows its metric, separately when rising and falling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Smoothing {
+    /// Time to (almost) catch up with a rise
+    pub attack: Duration,
+    /// Time to (almost) settle after a fall
+    pub release: Duration,
+}
+
+impl Smoothing {
+    /// Share of a change still left when the attack or release time is up
+    const REMAINING: f64 = 0.05;
+
+    /// Tick SlideInterval was tuned for (the Normal refresh rate)
+    const SLIDE_TICK: Duration = Duration::from_millis(250);
+
+    /// Smoothing equivalent to the old SlideInterval key, which moved 1/SlideInterval
+    /// of the way on every tick, at the Normal refresh rate
+    pub fn from_slide_interval(slide_interval: u32) -> Self {
+        let step = 1.0 / (slide_interval as f64).max(1.0);
+        let time = if step >= 1.0 {
+            Duration::ZERO
+        } else {
+            let ticks = Self::REMAINING.ln() / (1.0 - step).ln();
+            Self::SLIDE_TICK.mul_f64(ticks)
+        };
+        Self {
+            attack: time,
+            release: time,
+        }
+    }
+
+    /// Move `current` towards `target` by as much as `elapsed` allows
+    pub fn step(&self, current: f64, target: f64, elapsed: Duration) -> f64 {
+        let time = if target > current { self.attack } else { self.release };
+        if time.is_zero() {
+            return target;
+        }
+        let remaining = Self::REMAINING.powf(elapsed.as_secs_f64() / time.as_secs_f64());
+        target + (current - target) * remaining
+    }
+}
+
 /// Sound pack configuration (parsed from prefs.ini)
 #[derive(Debug, Clone)]
 pub struct SoundPackConfig {
@@ -32,14 +100,30 @@ pub struct SoundPackConfig {
     pub use_averages: bool,
     /// CPU channel sound mode
     pub cpu_mode: SoundMode,
-    /// RAM channel sound mode
-    pub ram_mode: SoundMode,
-    /// Disk channel sound mode
+    /// Disk channel sound mode (shared by the read/write channels)
     pub disk_mode: SoundMode,
-    /// Transition/slide interval (higher = smoother but slower)
-    pub slide_interval: u32,
+    /// Which PSI figure the pressure channels follow
+    pub pressure_stat: PressureStat,
+    /// Which figure the Load channel follows
+    pub load_source: LoadSource,
+    /// Which figure of the watched processes the Process channel follows
+    pub process_source: ProcessSource,
+    /// How quickly channels follow their metrics (AttackTime/ReleaseTime, or SlideInterval)
+    pub smoothing: Smoothing,
+    /// How long the end of each looping sound crossfades into its start
+    pub loop_crossfade: Duration,
     /// Enable pitch/frequency fluctuation
     pub frequency_fluctuation: bool,
+    /// What drives per-core CPU pitch
+    pub pitch_source: PitchSource,
+    /// How CPU load maps onto the CPU sounds
+    pub cpu_response: Response,
+    /// How disk activity maps onto the disk sounds (and per-device voices)
+    pub disk_response: Response,
+    /// How CPU load maps onto the CPU sounds' pitch
+    pub cpu_pitch: PitchResponse,
+    /// How disk activity maps onto the disk sounds' pitch
+    pub disk_pitch: PitchResponse,
 }
 
 impl Default for SoundPackConfig {
@@ -47,10 +131,18 @@ impl Default for SoundPackConfig {
         Self {
             use_averages: false,
             cpu_mode: SoundMode::Volume,
-            ram_mode: SoundMode::Volume,
             disk_mode: SoundMode::Volume,
-            slide_interval: 20,
+            pressure_stat: PressureStat::SomeAvg10,
+            load_source: LoadSource::Average,
+            process_source: ProcessSource::Cpu,
+            smoothing: Smoothing::from_slide_interval(20),
+            loop_crossfade: Duration::ZERO,
             frequency_fluctuation: false,
+            pitch_source: PitchSource::Load,
+            cpu_response: Response::default(),
+            disk_response: Response::default(),
+            cpu_pitch: PitchResponse::default(),
+            disk_pitch: PitchResponse::default(),
         }
     }
 }
@@ -92,6 +184,33 @@ impl ChannelSounds {
     }
 }
 
+/// A channel that follows a single metric
+#[derive(Debug, Clone)]
+pub struct MetricChannel {
+    /// Short name shown in the pack description
+    pub label: String,
+    /// Sound mode
+    pub mode: SoundMode,
+    /// ID of the metric the channel follows (e.g. "memory.usage")
+    pub metric: String,
+    /// Stereo position from -1.0 (left) to 1.0 (right)
+    pub pan: f64,
+    /// How the metric maps onto the sounds
+    pub response: Response,
+    /// How the metric maps onto pitch (with FrequencyFluctuation)
+    pub pitch: PitchResponse,
+    /// Sound files
+    pub sounds: ChannelSounds,
+}
+
+/// A sound played once whenever its metric fires (reads 1.0)
+#[derive(Debug, Clone)]
+pub struct MetricTrigger {
+    /// ID of the event metric (e.g. "power.connected")
+    pub metric: String,
+    pub sound: PathBuf,
+}
+
 /// A loaded sound pack with resolved file paths
 #[derive(Debug, Clone)]
 pub struct SoundPack {
@@ -103,10 +222,12 @@ pub struct SoundPack {
     pub config: SoundPackConfig,
     /// CPU sound files
     pub cpu_sounds: ChannelSounds,
-    /// RAM sound files
-    pub ram_sounds: ChannelSounds,
-    /// Disk sound files
+    /// Disk sound files, also used for per-device disk voices
     pub disk_sounds: ChannelSounds,
+    /// Channels that each follow a single metric (RAM, disk, network, ...)
+    pub channels: Vec<MetricChannel>,
+    /// One-shot sounds (power connected/disconnected)
+    pub triggers: Vec<MetricTrigger>,
 }
 
 impl SoundPack {
@@ -123,18 +244,17 @@ impl SoundPack {
             parts.push("Per-core CPU".to_string());
         }
 
-        let modes: Vec<&str> = [
-            ("CPU", self.config.cpu_mode),
-            ("RAM", self.config.ram_mode),
-            ("Disk", self.config.disk_mode),
-        ]
-        .iter()
-        .filter_map(|(name, mode)| match mode {
-            SoundMode::Disabled => None,
-            SoundMode::Volume => Some(*name),
-            SoundMode::Fade => Some(*name),
-        })
-        .collect();
+        let mut modes: Vec<&str> = Vec::new();
+        let channels = std::iter::once(("CPU", self.config.cpu_mode, self.cpu_sounds.has_sounds()))
+            .chain(self.channels.iter().map(|ch| (ch.label.as_str(), ch.mode, ch.sounds.has_sounds())));
+        for (label, mode, has_sounds) in channels {
+            // Channels without sound files are silent regardless of their mode
+            let audible = has_sounds && mode != SoundMode::Disabled;
+            // Split disk channels share one label
+            if audible && !modes.contains(&label) {
+                modes.push(label);
+            }
+        }
 
         if !modes.is_empty() {
             parts.push(format!("Monitors: {}", modes.join(", ")));
@@ -142,6 +262,36 @@ impl SoundPack {
 
         parts.join(" | ")
     }
+
+    /// Every sound file the pack plays, looping or one-shot, each once
+    pub fn sound_files(&self) -> Vec<PathBuf> {
+        let sounds = [&self.cpu_sounds, &self.disk_sounds]
+            .into_iter()
+            .chain(self.channels.iter().map(|ch| &ch.sounds));
+        let loops = sounds.flat_map(|s| s.primary.iter().chain(s.secondary.iter()));
+        let one_shots = self.triggers.iter().map(|t| &t.sound);
+
+        let mut files: Vec<PathBuf> = Vec::new();
+        for path in loops.chain(one_shots) {
+            if !files.contains(path) {
+                files.push(path.clone());
+            }
+        }
+        files
+    }
+
+    /// Every metric the pack's voices follow, each once
+    pub fn metric_ids(&self) -> Vec<String> {
+        // CPU and per-device disk voices aren't channels but follow fixed metrics
+        let fixed = ["cpu.average", "cpu.core", "cpu.frequency", "disk.device"];
+        let mut ids: Vec<String> = fixed.iter().map(|id| id.to_string()).collect();
+        for channel in &self.channels {
+            if !ids.contains(&channel.metric) {
+                ids.push(channel.metric.clone());
+            }
+        }
+        ids
+    }
 }
 
 #[derive(Debug)]
@@ -175,17 +325,47 @@ impl From<ini::Error> for SoundPackError {
     }
 }
 
+/// Pan position for the disk read channel (left)
+const DISK_READ_PAN: f64 = -1.0;
+/// Pan position for the disk write channel (right)
+const DISK_WRITE_PAN: f64 = 1.0;
+
+/// Built-in channels besides CPU: (name used for keys and sound files, description label)
+const BUILTIN_CHANNELS: &[(&str, &str)] = &[
+    ("RAM", "RAM"),
+    ("Disk", "Disk"),
+    ("Net", "Net"),
+    ("Temp", "Temp"),
+    ("CPUPressure", "CPU PSI"),
+    ("MemoryPressure", "RAM PSI"),
+    ("IOPressure", "IO PSI"),
+    ("Swap", "Swap"),
+    ("Battery", "Battery"),
+    ("Load", "Load"),
+    ("Blocked", "Blocked"),
+    ("Process", "Process"),
+];
+
 pub struct PackLoader {
     packs_directory: PathBuf,
+    /// User changes to channel responses, applied to every pack
+    response_overrides: Vec<ResponseOverride>,
 }
 
 impl PackLoader {
     pub fn new(packs_directory: impl Into<PathBuf>) -> Self {
         Self {
             packs_directory: packs_directory.into(),
+            response_overrides: Vec::new(),
         }
     }
 
+    /// Apply the user's response changes on top of every pack's own
+    pub fn with_response_overrides(mut self, overrides: &[ResponseOverride]) -> Self {
+        self.response_overrides = overrides.to_vec();
+        self
+    }
+
     /// Scan the packs directory and return all available packs
     pub fn scan_packs(&self) -> Result<Vec<SoundPack>, SoundPackError> {
         let mut packs = Vec::new();
@@ -214,12 +394,19 @@ impl PackLoader {
     /// Load a specific pack from a directory
     pub fn load_pack(&self, pack_dir: &Path) -> Result<SoundPack, SoundPackError> {
         let config_path = pack_dir.join("prefs.ini");
-        let ini = Ini::load_from_file(&config_path)?;
+        let mut ini = Ini::load_from_file(&config_path)?;
 
         let section = ini
-            .section(Some("soundpack"))
+            .section_mut(Some("soundpack"))
             .ok_or_else(|| SoundPackError::ParseError("Missing [soundpack] section".to_string()))?;
 
+        // Pack keys never hold free-form text, so `Key=value ; note` keeps just the value
+        for (_, value) in section.iter_mut() {
+            let len = strip_comment(value).len();
+            value.truncate(len);
+        }
+        let section = &*section;
+
         // Parse configuration
         let config = SoundPackConfig {
             use_averages: section
@@ -227,37 +414,87 @@ impl PackLoader {
                 .and_then(|v| v.parse().ok())
                 .map(|v: i32| v != 0)
                 .unwrap_or(false),
-            cpu_mode: section
-                .get("CPUSoundMode")
-                .and_then(|v| v.parse().ok())
-                .map(SoundMode::from_int)
-                .unwrap_or(SoundMode::Volume),
-            ram_mode: section
-                .get("RAMSoundMode")
-                .and_then(|v| v.parse().ok())
-                .map(SoundMode::from_int)
-                .unwrap_or(SoundMode::Volume),
-            disk_mode: section
-                .get("DiskSoundMode")
-                .and_then(|v| v.parse().ok())
-                .map(SoundMode::from_int)
-                .unwrap_or(SoundMode::Volume),
-            slide_interval: section
-                .get("SlideInterval")
-                .and_then(|v| v.parse().ok())
-                .unwrap_or(20),
+            cpu_mode: Self::sound_mode(section, "CPU"),
+            disk_mode: Self::sound_mode(section, "Disk"),
+            pressure_stat: section
+                .get("PressureStat")
+                .and_then(PressureStat::from_name)
+                .unwrap_or_default(),
+            load_source: section
+                .get("LoadSource")
+                .map(LoadSource::from_name)
+                .unwrap_or_default(),
+            process_source: section
+                .get("ProcessSource")
+                .map(ProcessSource::from_name)
+                .unwrap_or_default(),
+            smoothing: Self::smoothing(section),
+            loop_crossfade: section
+                .get("LoopCrossfade")
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|ms| *ms >= 0.0 && ms.is_finite())
+                .map(|ms| Duration::from_secs_f64(ms / 1000.0))
+                .unwrap_or(Duration::ZERO),
             frequency_fluctuation: section
                 .get("FrequencyFluctuation")
                 .and_then(|v| v.parse().ok())
                 .map(|v: i32| v != 0)
                 .unwrap_or(false),
+            pitch_source: section
+                .get("PitchSource")
+                .map(PitchSource::from_name)
+                .unwrap_or_default(),
+            cpu_response: self.response(section, "CPU"),
+            disk_response: self.response(section, "Disk"),
+            cpu_pitch: PitchResponse::read(section, "CPU"),
+            disk_pitch: PitchResponse::read(section, "Disk"),
         };
 
         // Resolve sound files based on modes
         let cpu_sounds = Self::resolve_sounds(pack_dir, "CPU", config.cpu_mode);
-        let ram_sounds = Self::resolve_sounds(pack_dir, "RAM", config.ram_mode);
         let disk_sounds = Self::resolve_sounds(pack_dir, "disk", config.disk_mode);
 
+        let mut channels = Vec::new();
+
+        // Built-in channels, each optionally rebound with a <Name>Metric key
+        for (name, label) in BUILTIN_CHANNELS {
+            if *name == "Disk" {
+                channels.extend(Self::disk_channels(pack_dir, &config, &disk_sounds));
+                continue;
+            }
+            let mode = Self::sound_mode(section, name);
+            let metric = section
+                .get(format!("{}Metric", name))
+                .map(|m| m.trim().to_string())
+                .unwrap_or_else(|| Self::default_metric(name, mode, &config));
+            let response = self.response(section, name);
+            channels.push(Self::metric_channel(pack_dir, section, name, label, mode, metric, response));
+        }
+
+        // Extra channels bound to any metric, listed as Channels=Name1, Name2
+        let extra_names = section.get("Channels").unwrap_or_default();
+        for name in extra_names.split(|c: char| c == ',' || c.is_whitespace()).filter(|n| !n.is_empty()) {
+            let Some(metric) = section.get(format!("{}Metric", name)) else {
+                eprintln!("Warning: Channel '{}' in {} has no {}Metric key", name, config_path.display(), name);
+                continue;
+            };
+            let mode = Self::sound_mode(section, name);
+            let metric = metric.trim().to_string();
+            let response = self.response(section, name);
+            channels.push(Self::metric_channel(pack_dir, section, name, name, mode, metric, response));
+        }
+
+        let triggers = [("PowerOn", "power.connected"), ("PowerOff", "power.disconnected")]
+            .into_iter()
+            .filter_map(|(name, metric)| {
+                let sound = Self::resolve_sounds(pack_dir, name, SoundMode::Volume).primary?;
+                Some(MetricTrigger {
+                    metric: metric.to_string(),
+                    sound,
+                })
+            })
+            .collect();
+
         // Get pack name from directory
         let name = pack_dir
             .file_name()
@@ -270,11 +507,143 @@ impl PackLoader {
             name,
             config,
             cpu_sounds,
-            ram_sounds,
             disk_sounds,
+            channels,
+            triggers,
         })
     }
 
+    /// Read AttackTime/ReleaseTime (milliseconds), falling back to SlideInterval
+    /// for whichever is missing
+    fn smoothing(section: &ini::Properties) -> Smoothing {
+        let slide_interval = section
+            .get("SlideInterval")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(20);
+        let mut smoothing = Smoothing::from_slide_interval(slide_interval);
+
+        let millis = |key: &str| {
+            section
+                .get(key)
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|ms| *ms >= 0.0 && ms.is_finite())
+                .map(|ms| Duration::from_secs_f64(ms / 1000.0))
+        };
+        if let Some(attack) = millis("AttackTime") {
+            smoothing.attack = attack;
+        }
+        if let Some(release) = millis("ReleaseTime") {
+            smoothing.release = release;
+        }
+
+        smoothing
+    }
+
+    /// Read a channel's <Name>SoundMode key
+    fn sound_mode(section: &ini::Properties, name: &str) -> SoundMode {
+        section
+            .get(format!("{}SoundMode", name))
+            .and_then(|v| v.parse().ok())
+            .map(SoundMode::from_int)
+            .unwrap_or(SoundMode::Volume)
+    }
+
+    /// A channel's response: the pack's keys, then the user's overrides
+    fn response(&self, section: &ini::Properties, name: &str) -> Response {
+        let mut response = Response::default();
+        ResponseOverride::read(section, name).apply(&mut response);
+        for user in &self.response_overrides {
+            if user.channel.eq_ignore_ascii_case(name) {
+                user.apply(&mut response);
+            }
+        }
+        response
+    }
+
+    /// Disk channels: separate read/write sounds replace the combined disk channel,
+    /// panned apart so reads and writes can be told apart
+    fn disk_channels(pack_dir: &Path, config: &SoundPackConfig, disk_sounds: &ChannelSounds) -> Vec<MetricChannel> {
+        let mode = config.disk_mode;
+        let read_sounds = Self::resolve_sounds(pack_dir, "diskread", mode);
+        let write_sounds = Self::resolve_sounds(pack_dir, "diskwrite", mode);
+
+        let channel = |sounds: ChannelSounds, metric: &str, pan: f64| MetricChannel {
+            label: "Disk".to_string(),
+            mode,
+            metric: metric.to_string(),
+            pan,
+            response: config.disk_response.clone(),
+            pitch: config.disk_pitch.clone(),
+            sounds,
+        };
+
+        if read_sounds.has_sounds() || write_sounds.has_sounds() {
+            // A direction without its own sound falls back to the combined disk sound
+            let or_disk = |sounds: ChannelSounds| {
+                if sounds.has_sounds() {
+                    sounds
+                } else {
+                    disk_sounds.clone()
+                }
+            };
+            vec![
+                channel(or_disk(read_sounds), "disk.read", DISK_READ_PAN),
+                channel(or_disk(write_sounds), "disk.write", DISK_WRITE_PAN),
+            ]
+        } else {
+            vec![channel(disk_sounds.clone(), "disk.activity", 0.0)]
+        }
+    }
+
+    /// Build a metric channel, with its sounds, pitch keys and optional <Name>Pan key
+    fn metric_channel(
+        pack_dir: &Path,
+        section: &ini::Properties,
+        name: &str,
+        label: &str,
+        mode: SoundMode,
+        metric: String,
+        response: Response,
+    ) -> MetricChannel {
+        let pan = section
+            .get(format!("{}Pan", name))
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(|p| p.clamp(-1.0, 1.0))
+            .unwrap_or(0.0); // center
+
+        MetricChannel {
+            label: label.to_string(),
+            mode,
+            metric,
+            pan,
+            response,
+            pitch: PitchResponse::read(section, name),
+            sounds: Self::resolve_sounds(pack_dir, name, mode),
+        }
+    }
+
+    /// Metric a built-in channel follows unless the pack overrides it
+    fn default_metric(name: &str, mode: SoundMode, config: &SoundPackConfig) -> String {
+        match name {
+            "RAM" => "memory.usage".to_string(),
+            "Net" => "network.activity".to_string(),
+            "Temp" => "temperature.level".to_string(),
+            "CPUPressure" => config.pressure_stat.metric_id("cpu"),
+            "MemoryPressure" => config.pressure_stat.metric_id("memory"),
+            "IOPressure" => config.pressure_stat.metric_id("io"),
+            // In fade mode the active sound only cuts in once paging starts
+            "Swap" if mode == SoundMode::Fade => "swap.paging".to_string(),
+            "Swap" => "swap.usage".to_string(),
+            // In fade mode it crossfades towards the active sound as charge runs out
+            "Battery" if mode == SoundMode::Fade => "battery.depletion".to_string(),
+            "Battery" => "battery.drain".to_string(),
+            "Load" => config.load_source.metric_id().to_string(),
+            "Blocked" => "load.blocked".to_string(),
+            "Process" => config.process_source.metric_id().to_string(),
+            _ => String::new(),
+        }
+    }
+
     /// Resolve sound files for a channel based on its mode
     fn resolve_sounds(pack_dir: &Path, base_name: &str, mode: SoundMode) -> ChannelSounds {
         if mode == SoundMode::Disabled {
@@ -321,3 +690,32 @@ impl PackLoader {
         ChannelSounds::none()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(prefs: &str) -> SoundPack {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("prefs.ini"), prefs).unwrap();
+        PackLoader::new(dir.path()).load_pack(dir.path()).unwrap()
+    }
+
+    #[test]
+    fn inline_comments_are_stripped_from_pack_keys() {
+        let pack = load(
+            "[soundpack]\n\
+             AttackTime=300         ; ms to follow a rise\n\
+             ReleaseTime=2000       ; ms to settle after a fall\n\
+             PressureStat=full      ; some or full\n\
+             ProcessSource=io # of the watched processes\n\
+             UseAverages=1 ;averaged CPU\n",
+        );
+        let config = &pack.config;
+        assert_eq!(config.smoothing.attack, Duration::from_millis(300));
+        assert_eq!(config.smoothing.release, Duration::from_millis(2000));
+        assert_eq!(config.pressure_stat, PressureStat::FullAvg10);
+        assert_eq!(config.process_source, ProcessSource::Io);
+        assert!(config.use_averages);
+    }
+}
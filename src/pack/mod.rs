@@ -1,3 +1,5 @@
+mod curve;
 mod loader;
 
-pub use loader::{PackLoader, SoundMode, SoundPack};
+pub use curve::{PitchResponse, Response, ResponseOverride};
+pub use loader::{PackLoader, PitchSource, Smoothing, SoundMode, SoundPack};
@@ -13,6 +13,7 @@ pub struct StartupDialog {
     list_box: ListBox,
     description_label: Label,
     start_button: Button,
+    preview_button: Button,
     selected_index: Option<usize>,
 }
 
@@ -50,12 +51,12 @@ impl StartupDialog {
 
         // Instructions label
         let instructions = Label::new(Some(
-            "Choose a sound pack to represent your system's CPU, RAM, and disk activity.",
+            "Choose a sound pack to represent your system's CPU, RAM, disk, and network activity.",
         ));
         instructions.set_halign(Align::Start);
         instructions.set_line_wrap(true);
         if let Some(accessible) = instructions.accessible() {
-            accessible.set_name("Choose a sound pack to represent your system's CPU, RAM, and disk activity.");
+            accessible.set_name("Choose a sound pack to represent your system's CPU, RAM, disk, and network activity.");
         }
         main_box.pack_start(&instructions, false, false, 0);
 
@@ -108,6 +109,13 @@ impl StartupDialog {
             accessible.set_description("Close the application without starting");
         }
 
+        let preview_button = Button::with_label("Preview");
+        preview_button.set_sensitive(false);
+        if let Some(accessible) = preview_button.accessible() {
+            accessible.set_name("Preview");
+            accessible.set_description("Play the selected sound pack with a simulated load, from idle to full");
+        }
+
         let start_button = Button::with_label("Start Monitoring");
         start_button.set_sensitive(false);
         start_button.style_context().add_class("suggested-action");
@@ -117,6 +125,7 @@ impl StartupDialog {
         }
 
         button_box.pack_start(&quit_button, false, false, 0);
+        button_box.pack_start(&preview_button, false, false, 0);
         button_box.pack_start(&start_button, false, false, 0);
         main_box.pack_start(&button_box, false, false, 0);
 
@@ -166,6 +175,7 @@ impl StartupDialog {
             list_box,
             description_label,
             start_button,
+            preview_button,
             selected_index: None,
         }
     }
@@ -231,6 +241,7 @@ impl StartupDialog {
     {
         let description_label = self.description_label.clone();
         let start_button = self.start_button.clone();
+        let preview_button = self.preview_button.clone();
 
         self.list_box.connect_row_selected(move |_, row| {
             if let Some(row) = row {
@@ -244,6 +255,7 @@ impl StartupDialog {
                                 accessible.set_name(&desc);
                             }
                             start_button.set_sensitive(true);
+                            preview_button.set_sensitive(true);
                             callback(Some(index));
                             return;
                         }
@@ -252,6 +264,7 @@ impl StartupDialog {
             }
             description_label.set_text("Select a pack to see its description.");
             start_button.set_sensitive(false);
+            preview_button.set_sensitive(false);
             callback(None);
         });
     }
@@ -266,6 +279,31 @@ impl StartupDialog {
         });
     }
 
+    /// Connect handler for preview button clicks. The callback returns whether a
+    /// preview is now playing, which the button's label follows.
+    pub fn connect_preview<F>(&self, callback: F)
+    where
+        F: Fn() -> bool + 'static,
+    {
+        self.preview_button.connect_clicked(move |button| {
+            let playing = callback();
+            Self::show_previewing(button, playing);
+        });
+    }
+
+    /// Update the preview button after a preview was started or stopped elsewhere
+    pub fn set_previewing(&self, playing: bool) {
+        Self::show_previewing(&self.preview_button, playing);
+    }
+
+    fn show_previewing(button: &Button, playing: bool) {
+        let label = if playing { "Stop Preview" } else { "Preview" };
+        button.set_label(label);
+        if let Some(accessible) = button.accessible() {
+            accessible.set_name(label);
+        }
+    }
+
     /// Get the window widget
     pub fn window(&self) -> &Window {
         &self.window
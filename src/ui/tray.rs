@@ -34,6 +34,11 @@ pub struct TrayCallbacks {
     pub on_cpu_toggled: Box<dyn Fn(bool)>,
     pub on_ram_toggled: Box<dyn Fn(bool)>,
     pub on_disk_toggled: Box<dyn Fn(bool)>,
+    pub on_network_toggled: Box<dyn Fn(bool)>,
+    /// Every channel not covered by the CPU, RAM, Disk and Network toggles
+    pub on_other_toggled: Box<dyn Fn(bool)>,
+    /// Called with the process name to watch, or None to stop watching
+    pub on_process_selected: Box<dyn Fn(Option<String>)>,
     pub on_show_window: Box<dyn Fn()>,
     pub on_quit: Box<dyn Fn()>,
 }
@@ -46,6 +51,9 @@ impl Default for TrayCallbacks {
             on_cpu_toggled: Box::new(|_| {}),
             on_ram_toggled: Box::new(|_| {}),
             on_disk_toggled: Box::new(|_| {}),
+            on_network_toggled: Box::new(|_| {}),
+            on_other_toggled: Box::new(|_| {}),
+            on_process_selected: Box::new(|_| {}),
             on_show_window: Box::new(|| {}),
             on_quit: Box::new(|| {}),
         }
@@ -61,6 +69,13 @@ pub struct TrayManager {
     cpu_item: gtk::CheckMenuItem,
     ram_item: gtk::CheckMenuItem,
     disk_item: gtk::CheckMenuItem,
+    network_item: gtk::CheckMenuItem,
+    other_item: gtk::CheckMenuItem,
+    // Rebuilt from the busiest processes while running
+    process_menu: gtk::Menu,
+    // Entries the process submenu was last built with
+    process_entries: RefCell<Vec<Option<String>>>,
+    watched_process: Rc<RefCell<Option<String>>>,
 }
 
 impl TrayManager {
@@ -164,6 +179,28 @@ impl TrayManager {
         });
         menu.append(&disk_item);
 
+        let network_item = gtk::CheckMenuItem::with_label("Monitor Network");
+        network_item.set_active(true);
+        let callbacks_ref = callbacks.clone();
+        network_item.connect_toggled(move |item| {
+            (callbacks_ref.borrow().on_network_toggled)(item.is_active());
+        });
+        menu.append(&network_item);
+
+        let other_item = gtk::CheckMenuItem::with_label("Monitor Other Channels");
+        other_item.set_active(true);
+        let callbacks_ref = callbacks.clone();
+        other_item.connect_toggled(move |item| {
+            (callbacks_ref.borrow().on_other_toggled)(item.is_active());
+        });
+        menu.append(&other_item);
+
+        // Process submenu (filled by set_top_processes)
+        let process_item = gtk::MenuItem::with_label("Watch Process");
+        let process_menu = gtk::Menu::new();
+        process_item.set_submenu(Some(&process_menu));
+        menu.append(&process_item);
+
         menu.append(&gtk::SeparatorMenuItem::new());
 
         // Show window
@@ -194,6 +231,11 @@ impl TrayManager {
             cpu_item,
             ram_item,
             disk_item,
+            network_item,
+            other_item,
+            process_menu,
+            process_entries: RefCell::new(Vec::new()),
+            watched_process: Rc::new(RefCell::new(None)),
         }
     }
 
@@ -223,6 +265,70 @@ impl TrayManager {
         self.disk_item.set_active(enabled);
     }
 
+    pub fn set_network_enabled(&self, enabled: bool) {
+        self.network_item.set_active(enabled);
+    }
+
+    pub fn set_other_enabled(&self, enabled: bool) {
+        self.other_item.set_active(enabled);
+    }
+
+    /// Rebuild the process submenu from the busiest process names, in alphabetical order.
+    /// `watched` is the process currently being watched, kept in the list even when idle.
+    /// The submenu is only rebuilt when its entries change, so it doesn't shift under the
+    /// user (or a screen reader) while open.
+    pub fn set_top_processes(&self, names: &[String], watched: Option<&str>) {
+        let mut names: Vec<String> = names.to_vec();
+        if let Some(name) = watched {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        let entries: Vec<Option<String>> =
+            std::iter::once(None).chain(names.into_iter().map(Some)).collect();
+
+        let watched = watched.map(|s| s.to_string());
+        if *self.process_entries.borrow() == entries && *self.watched_process.borrow() == watched {
+            return;
+        }
+        *self.watched_process.borrow_mut() = watched;
+        *self.process_entries.borrow_mut() = entries.clone();
+
+        for child in self.process_menu.children() {
+            self.process_menu.remove(&child);
+        }
+
+        let mut group: Option<gtk::RadioMenuItem> = None;
+        for entry in entries {
+            let label = entry.as_deref().unwrap_or("None (stop watching)");
+            let item = if let Some(ref grp) = group {
+                gtk::RadioMenuItem::with_label_from_widget(grp, Some(label))
+            } else {
+                gtk::RadioMenuItem::with_label(label)
+            };
+
+            // Mark the current choice before connecting, so rebuilding doesn't fire the callback
+            item.set_active(entry == *self.watched_process.borrow());
+
+            let callbacks_ref = self.callbacks.clone();
+            let watched_ref = self.watched_process.clone();
+            item.connect_toggled(move |item| {
+                if item.is_active() && *watched_ref.borrow() != entry {
+                    *watched_ref.borrow_mut() = entry.clone();
+                    (callbacks_ref.borrow().on_process_selected)(entry.clone());
+                }
+            });
+
+            self.process_menu.append(&item);
+            if group.is_none() {
+                group = Some(item);
+            }
+        }
+
+        self.process_menu.show_all();
+    }
+
     /// Hide the tray icon
     pub fn hide(&mut self) {
         self.indicator.set_status(AppIndicatorStatus::Passive);